@@ -60,8 +60,10 @@ fn main() -> Result<(), Box<dyn Error>> {
         panic!("{}", e);
     });
 
-    // Limit to max ~60 fps update rate
-    window.limit_update_rate(Some(std::time::Duration::from_micros(16666)));
+    // Limit the update rate to the frame rate of the console, ~60 or ~50 fps
+    window.limit_update_rate(Some(std::time::Duration::from_secs_f64(
+        1.0 / nes.frame_rate(),
+    )));
 
     let mut fps_avg = MovingAvg::new(30);
     let mut time = std::time::SystemTime::now();
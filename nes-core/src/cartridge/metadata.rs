@@ -0,0 +1,228 @@
+//! Properties of a cartridge, as described by its ROM file's header.
+
+use super::{CartridgeHeader, CartridgeMirror};
+
+/// Format of a ROM file's header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CartridgeFormat {
+    /// iNES header written by old tools, which may have left garbage
+    /// in bytes 7-15. Only the low nibble of the mapper number is used.
+    ArchaicINes,
+    INes,
+    Nes2,
+}
+
+/// CPU/PPU timing the game was made for.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CartridgeRegion {
+    #[default]
+    Ntsc,
+    Pal,
+    /// Works on both NTSC and PAL consoles
+    Multi,
+    Dendy,
+}
+
+/// Console the game was made for.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsoleType {
+    #[default]
+    Nes,
+    VsSystem,
+    Playchoice10,
+    /// Extended console type from byte 13 of a NES 2.0 header
+    Extended(u8),
+}
+
+/// Read-only information about a cartridge.
+///
+/// All sizes are in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CartridgeMetadata {
+    pub format: CartridgeFormat,
+    pub mapper: u16,
+    /// Always 0 for iNES headers
+    pub submapper: u8,
+
+    pub program_rom_size: usize,
+    pub character_rom_size: usize,
+    /// Volatile work RAM
+    pub program_ram_size: usize,
+    /// Battery-backed work RAM
+    pub program_nvram_size: usize,
+    pub character_ram_size: usize,
+    /// Battery-backed character RAM
+    pub character_nvram_size: usize,
+
+    /// Mirroring hardwired by the cartridge. Ignored by mappers that
+    /// control the mirroring.
+    pub mirror: CartridgeMirror,
+    /// The cartridge provides its own VRAM for four nametables
+    pub four_screen: bool,
+    /// The cartridge contains battery-backed memory
+    pub battery: bool,
+    /// A 512 byte trainer precedes the program ROM
+    pub trainer: bool,
+
+    pub region: CartridgeRegion,
+    pub console_type: ConsoleType,
+}
+
+impl CartridgeHeader {
+    pub(crate) fn format(&self) -> CartridgeFormat {
+        // bytes 12-15 are unused by iNES
+        let unused = [self.timing, self.system_type, self.misc[0], self.misc[1]];
+
+        match self.mapper2 & 0x0C {
+            0x08 => CartridgeFormat::Nes2,
+            0x00 if unused == [0; 4] => CartridgeFormat::INes,
+            _ => CartridgeFormat::ArchaicINes,
+        }
+    }
+
+    pub(crate) fn metadata(&self) -> CartridgeMetadata {
+        let format = self.format();
+        let nes2 = format == CartridgeFormat::Nes2;
+
+        let mut mapper = (self.mapper1 >> 4) as u16;
+        let mut submapper = 0;
+        if format != CartridgeFormat::ArchaicINes {
+            mapper |= (self.mapper2 & 0xF0) as u16;
+        }
+        if nes2 {
+            mapper |= ((self.program_ram_size & 0x0F) as u16) << 8;
+            submapper = self.program_ram_size >> 4;
+        }
+
+        let battery = self.mapper1 & 0x02 != 0;
+
+        let (program_rom_size, character_rom_size) = if nes2 {
+            (
+                rom_size(self.program_rom_chunks, self.tv_system1 & 0x0F, 16384),
+                rom_size(self.character_rom_chunks, self.tv_system1 >> 4, 8192),
+            )
+        } else {
+            (
+                self.program_rom_chunks as usize * 16384,
+                self.character_rom_chunks as usize * 8192,
+            )
+        };
+
+        let (program_ram_size, program_nvram_size, character_ram_size, character_nvram_size) =
+            if nes2 {
+                (
+                    ram_size(self.tv_system2 & 0x0F),
+                    ram_size(self.tv_system2 >> 4),
+                    ram_size(self.character_ram_size & 0x0F),
+                    ram_size(self.character_ram_size >> 4),
+                )
+            } else {
+                // the size of the program RAM is given in 8 KB units, where 0 infers 8 KB
+                let program_ram = match format {
+                    CartridgeFormat::INes => self.program_ram_size.max(1) as usize * 8192,
+                    _ => 8192,
+                };
+                // cartridges without CHR-ROM have 8 KB of CHR-RAM
+                let character_ram = if self.character_rom_chunks == 0 {
+                    8192
+                } else {
+                    0
+                };
+
+                if battery {
+                    (0, program_ram, character_ram, 0)
+                } else {
+                    (program_ram, 0, character_ram, 0)
+                }
+            };
+
+        let region = match format {
+            CartridgeFormat::Nes2 => match self.timing & 0x03 {
+                0 => CartridgeRegion::Ntsc,
+                1 => CartridgeRegion::Pal,
+                2 => CartridgeRegion::Multi,
+                _ => CartridgeRegion::Dendy,
+            },
+            CartridgeFormat::INes if self.tv_system1 & 0x01 != 0 => CartridgeRegion::Pal,
+            _ => CartridgeRegion::Ntsc,
+        };
+
+        let console_type = match (format, self.mapper2 & 0x03) {
+            (CartridgeFormat::ArchaicINes, _) => ConsoleType::Nes,
+            (_, 1) => ConsoleType::VsSystem,
+            (_, 2) => ConsoleType::Playchoice10,
+            (CartridgeFormat::Nes2, 3) => ConsoleType::Extended(self.system_type & 0x0F),
+            _ => ConsoleType::Nes,
+        };
+
+        CartridgeMetadata {
+            format,
+            mapper,
+            submapper,
+
+            program_rom_size,
+            character_rom_size,
+            program_ram_size,
+            program_nvram_size,
+            character_ram_size,
+            character_nvram_size,
+
+            mirror: if self.mapper1 & 0x01 != 0 {
+                CartridgeMirror::Vertical
+            } else {
+                CartridgeMirror::Horizontal
+            },
+            four_screen: self.mapper1 & 0x08 != 0,
+            battery,
+            trainer: self.mapper1 & 0x04 != 0,
+
+            region,
+            console_type,
+        }
+    }
+}
+
+/// Size of a NES 2.0 ROM area, given the low byte and high nibble of its
+/// size in units of `unit` bytes.
+fn rom_size(lsb: u8, msb: u8, unit: usize) -> usize {
+    if msb == 0x0F {
+        // exponent-multiplier notation: 2^E * (MM * 2 + 1)
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0x03) as usize * 2 + 1;
+        2usize.saturating_pow(exponent).saturating_mul(multiplier)
+    } else {
+        (((msb as usize) << 8) | lsb as usize) * unit
+    }
+}
+
+/// Size of a NES 2.0 RAM area, given its shift count.
+fn ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
+
+#[test]
+fn test_nes2_header() {
+    let mut bytes = super::ines_rom(0x01, 2, 0, 0x03);
+    // NES 2.0 fields: mapper 0x101, submapper 2, 8 KB of PRG-NVRAM and
+    // CHR-RAM, PAL timing, PlayChoice-10
+    bytes[7..13].copy_from_slice(&[0x0A, 0x21, 0x00, 0x70, 0x07, 0x01]);
+    let header: CartridgeHeader =
+        binread::BinReaderExt::read_be(&mut binread::io::Cursor::new(&bytes)).unwrap();
+    let metadata = header.metadata();
+
+    assert_eq!(metadata.format, CartridgeFormat::Nes2);
+    assert_eq!(metadata.mapper, 0x101);
+    assert_eq!(metadata.submapper, 2);
+    assert_eq!(metadata.program_rom_size, 2 * 16384);
+    assert_eq!(metadata.program_ram_size, 0);
+    assert_eq!(metadata.program_nvram_size, 8192);
+    assert_eq!(metadata.character_ram_size, 8192);
+    assert_eq!(metadata.mirror, CartridgeMirror::Vertical);
+    assert!(metadata.battery);
+    assert_eq!(metadata.region, CartridgeRegion::Pal);
+    assert_eq!(metadata.console_type, ConsoleType::Playchoice10);
+}
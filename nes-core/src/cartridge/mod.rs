@@ -7,26 +7,42 @@ use std::rc::Rc;
 use binread::{BinRead, BinReaderExt};
 use thiserror::Error;
 
-use crate::system::mapper::{mappers, Mapper};
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::mapper::{mappers, Mapped, Mapper};
+use crate::util;
+
+mod metadata;
+
+pub use metadata::{CartridgeFormat, CartridgeMetadata, CartridgeRegion, ConsoleType};
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct Cartridge {
     pub(crate) mirror: CartridgeMirror,
     pub(crate) header: CartridgeHeader,
+    metadata: CartridgeMetadata,
 
-    program_memory: Vec<u8>,
-    character_memory: Vec<u8>,
+    // ROM is never written to, clones of the cartridge share it
+    program_memory: Rc<[u8]>,
+    /// Empty if the cartridge has CHR-RAM
+    character_rom: Rc<[u8]>,
+    /// Empty if the cartridge has CHR-ROM
+    character_ram: Vec<u8>,
+    /// Work RAM, mapped by some mappers to $6000-$7FFF
+    program_ram: Vec<u8>,
+    /// CRC-32 of the PRG-ROM and CHR-ROM, identifies the game in save states
+    rom_crc32: u32,
+    rom_md5: [u8; 16],
 
     // These fields might be used later, it's best if we keep them
-    mapper_id: u8,
+    mapper_id: u16,
     program_banks: u8,
     character_banks: u8,
 
-    mapper: Rc<dyn Mapper>,
+    mapper: Box<dyn Mapper>,
 }
 
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CartridgeMirror {
     #[default]
     Horizontal,
@@ -35,7 +51,10 @@ pub enum CartridgeMirror {
     OneScreenHigh,
 }
 
-/// Format header for iNES
+/// Format header for iNES and NES 2.0
+///
+/// Bytes 8-10 have different meanings in NES 2.0 headers,
+/// the field names follow iNES.
 #[derive(BinRead, Debug, Clone, Copy)]
 #[allow(dead_code)]
 pub struct CartridgeHeader {
@@ -45,12 +64,20 @@ pub struct CartridgeHeader {
     mapper1: u8,
     mapper2: u8,
 
-    // These fields might be used later, it's best if we keep them
+    /// NES 2.0: mapper MSB and submapper
     program_ram_size: u8,
+    /// NES 2.0: PRG-ROM and CHR-ROM size MSB
     tv_system1: u8,
+    /// NES 2.0: PRG-RAM and PRG-NVRAM shift counts
     tv_system2: u8,
 
-    _unused: [u8; 5],
+    // NES 2.0 only
+    /// CHR-RAM and CHR-NVRAM shift counts
+    character_ram_size: u8,
+    timing: u8,
+    system_type: u8,
+    /// Misc. ROMs and default expansion device
+    misc: [u8; 2],
 }
 
 /// Cartridge Error
@@ -59,6 +86,7 @@ pub struct CartridgeHeader {
 /// - HeaderError: Could not read the file's header
 /// - FileTypeError: Unknown file type for cartridge
 /// - UnimplementedError: Functionality not yet implemented
+/// - SaveError: Could not load a save file into the cartridge
 #[derive(Error, Debug)]
 pub enum CartridgeError {
     #[error("Could not read ROM file: {0}")]
@@ -69,6 +97,8 @@ pub enum CartridgeError {
     FileTypeError(u8),
     #[error("Unimplemented: {0}")]
     UnimplementedError(String),
+    #[error("Invalid save file: {0}")]
+    SaveError(String),
 }
 
 impl Cartridge {
@@ -80,94 +110,350 @@ impl Cartridge {
         let mut reader = binread::io::Cursor::new(bytes);
 
         let header: CartridgeHeader = reader.read_be()?;
+        let metadata = header.metadata();
 
-        if header.mapper1 & 0x04 != 0 {
+        if metadata.trainer {
             // skip 512 bytes
             reader.seek(SeekFrom::Current(512))?;
         }
 
-        let mut program_memory: Vec<u8> = Vec::new();
-        let mut character_memory: Vec<u8> = Vec::new();
-
-        let mapper_id: u8 = ((header.mapper2 >> 4) << 4) | (header.mapper1 >> 4);
-        let program_banks: u8;
-        let character_banks: u8;
+        // the mappers count memory in 16 KB PRG banks and 8 KB CHR banks
+        let program_banks = banks(metadata.program_rom_size, 16384)?;
 
-        let mirror = if header.mapper1 & 0x01 != 0 {
-            CartridgeMirror::Vertical
+        // cartridges without CHR-ROM have CHR-RAM, 8 KB unless the header tells otherwise
+        let has_character_ram = metadata.character_rom_size == 0;
+        let character_size = if has_character_ram {
+            (metadata.character_ram_size + metadata.character_nvram_size).max(8192)
         } else {
-            CartridgeMirror::Horizontal
+            metadata.character_rom_size
         };
+        let character_banks = banks(character_size, 8192)?;
 
-        let file_type: u8 = 1;
+        let mut program_memory = vec![0; program_banks as usize * 16384];
+        reader.read_exact(&mut program_memory[..metadata.program_rom_size])?;
 
-        match file_type {
-            0 => return Err(CartridgeError::UnimplementedError("file type 0".into())),
-            1 => {
-                program_banks = header.program_rom_chunks;
-                program_memory.resize(program_banks as usize * 16384, 0);
-                reader.read_exact(&mut program_memory)?;
+        let mut character_memory = vec![0; character_banks as usize * 8192];
+        reader.read_exact(&mut character_memory[..metadata.character_rom_size])?;
 
-                character_banks = header.character_rom_chunks;
-                character_memory.resize(character_banks as usize * 8192, 0);
-                reader.read_exact(&mut character_memory)?;
-            }
-            2 => return Err(CartridgeError::UnimplementedError("file type 2".into())),
-            _ => return Err(CartridgeError::FileTypeError(file_type)),
+        let (character_rom, character_ram) = if has_character_ram {
+            (Rc::from([]), character_memory)
+        } else {
+            (Rc::from(character_memory), Vec::new())
         };
 
-        let mapper = match mapper_id {
-            0 => Rc::new(mappers::Mapper0::new(program_banks, character_banks)),
-            _ => {
+        // iNES headers always imply 8 KB of PRG-RAM, so boards that have
+        // none only get some from NES 2.0 headers
+        let has_program_ram = metadata.format == CartridgeFormat::Nes2
+            && metadata.program_ram_size + metadata.program_nvram_size > 0;
+
+        let mapper: Box<dyn Mapper> = match metadata.mapper {
+            0 => Box::new(mappers::Mapper0::new(program_banks, character_banks)),
+            1 => Box::new(mappers::Mapper1::new(program_banks, character_banks)),
+            2 => Box::new(mappers::Mapper2::new(
+                program_banks,
+                character_banks,
+                has_program_ram,
+            )),
+            3 => Box::new(mappers::Mapper3::new(
+                program_banks,
+                character_banks,
+                has_program_ram,
+            )),
+            4 => Box::new(mappers::Mapper4::new(program_banks, character_banks)),
+            7 => Box::new(mappers::Mapper7::new(program_banks, character_banks)),
+            66 => Box::new(mappers::Mapper66::new(program_banks, character_banks)),
+            mapper_id => {
                 return Err(CartridgeError::UnimplementedError(format!(
                     "mapper with id {mapper_id}"
                 )))
             }
         };
 
+        let program_ram = vec![0; metadata.program_ram_size + metadata.program_nvram_size];
+
+        let rom_size = metadata.program_rom_size + metadata.character_rom_size;
+        let rom_start = reader.position() as usize - rom_size;
+        let rom_crc32 = util::crc32(&bytes[rom_start..rom_start + rom_size]);
+        let rom_md5 = util::md5(&bytes[rom_start..rom_start + rom_size]);
+
         Ok(Cartridge {
-            program_memory,
-            character_memory,
+            program_memory: Rc::from(program_memory),
+            character_rom,
+            character_ram,
+            program_ram,
+            rom_crc32,
+            rom_md5,
 
-            mapper_id,
+            mapper_id: metadata.mapper,
             program_banks,
             character_banks,
 
-            mirror,
+            mirror: metadata.mirror,
             header,
+            metadata,
             mapper,
         })
     }
 
+    /// Properties of the cartridge read from its header.
+    pub fn metadata(&self) -> &CartridgeMetadata {
+        &self.metadata
+    }
+
+    /// Battery-backed program RAM, in the format of `.sav` files.
+    ///
+    /// Returns `None` if the cartridge has no battery.
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        let size = self.metadata.program_nvram_size;
+
+        if !self.metadata.battery || size == 0 {
+            return None;
+        }
+        Some(&self.program_ram[..size])
+    }
+
+    /// Restores the battery-backed program RAM from a `.sav` file.
+    pub fn load_battery_ram(&mut self, data: &[u8]) -> Result<(), CartridgeError> {
+        let size = match self.battery_ram() {
+            Some(ram) => ram.len(),
+            None => return Err(CartridgeError::SaveError("cartridge has no battery".into())),
+        };
+
+        if data.len() != size {
+            return Err(CartridgeError::SaveError(format!(
+                "expected {size} bytes, found {}",
+                data.len()
+            )));
+        }
+
+        self.program_ram[..size].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// CRC-32 of the ROM, excluding the header.
+    pub(crate) fn rom_crc32(&self) -> u32 {
+        self.rom_crc32
+    }
+
+    /// MD5 of the ROM, excluding the header, as used by FCEUX.
+    pub(crate) fn rom_md5(&self) -> [u8; 16] {
+        self.rom_md5
+    }
+
+    /// Writes the cartridge's RAM and the mapper's registers to a save state.
+    pub(crate) fn save_state(&self, state: &mut StateWriter) {
+        state.write_bytes(&self.program_ram);
+        state.write_bytes(&self.character_ram);
+        self.mapper.save_state(state);
+    }
+
+    pub(crate) fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.program_ram)?;
+        state.read_bytes(&mut self.character_ram)?;
+        self.mapper.load_state(state)
+    }
+
+    /// Nametable mirroring currently used by the cartridge.
+    ///
+    /// Some mappers may change the mirroring while the game runs.
+    pub fn mirror(&self) -> CartridgeMirror {
+        self.mapper.mirror().unwrap_or(self.mirror)
+    }
+
+    /// Puts the mapper back into its power-up state.
+    pub fn reset(&mut self) {
+        self.mapper.reset();
+    }
+
+    /// Notifies the mapper of a rise of the PPU's A12 address line.
+    pub fn ppu_a12_rise(&mut self) {
+        self.mapper.ppu_a12_rise();
+    }
+
+    /// Returns true while the cartridge requests an interrupt.
+    pub fn irq_pending(&self) -> bool {
+        self.mapper.irq_pending()
+    }
+
     pub fn cpu_map_read(&self, addr: u16) -> Option<u8> {
-        self.mapper
-            .cpu_map_read(addr)
-            .map(|mapped_addr| self.program_memory[mapped_addr as usize])
+        match self.mapper.cpu_map_read(addr) {
+            Mapped::Memory(mapped_addr) => Some(self.program_memory[mapped_addr as usize]),
+            Mapped::Ram(mapped_addr) => self.program_ram.get(mapped_addr as usize).copied(),
+            Mapped::Register | Mapped::Unmapped => None,
+        }
     }
 
-    pub fn cpu_map_write(&mut self, addr: u16, data: u8) -> Option<u8> {
-        self.mapper.cpu_map_write(addr, data).map(|mapped_addr| {
-            self.program_memory[mapped_addr as usize] = data;
-            self.program_memory[mapped_addr as usize]
-        })
+    /// Returns true if the write was handled by the cartridge.
+    ///
+    /// Writes to program memory are ignored, since it is read-only.
+    pub fn cpu_map_write(&mut self, addr: u16, data: u8) -> bool {
+        let data = if self.mapper.bus_conflicts() {
+            match self.mapper.cpu_map_read(addr) {
+                Mapped::Memory(mapped_addr) => data & self.program_memory[mapped_addr as usize],
+                _ => data,
+            }
+        } else {
+            data
+        };
+
+        match self.mapper.cpu_map_write(addr, data) {
+            Mapped::Ram(mapped_addr) => {
+                if let Some(byte) = self.program_ram.get_mut(mapped_addr as usize) {
+                    *byte = data;
+                }
+                true
+            }
+            Mapped::Memory(_) | Mapped::Register => true,
+            Mapped::Unmapped => false,
+        }
+    }
+
+    /// Writes program RAM without going through the mapper's registers.
+    ///
+    /// Returns false if `addr` isn't mapped to program RAM.
+    pub(crate) fn cpu_poke(&mut self, addr: u16, data: u8) -> bool {
+        let Mapped::Ram(mapped_addr) = self.mapper.cpu_map_read(addr) else {
+            return false;
+        };
+
+        match self.program_ram.get_mut(mapped_addr as usize) {
+            Some(byte) => {
+                *byte = data;
+                true
+            }
+            None => false,
+        }
     }
 
     pub fn ppu_map_read(&self, addr: u16) -> Option<u8> {
-        self.mapper
-            .ppu_map_read(addr)
-            .map(|mapped_addr| self.character_memory[mapped_addr as usize])
+        match self.mapper.ppu_map_read(addr) {
+            Mapped::Memory(mapped_addr) if self.character_ram.is_empty() => {
+                Some(self.character_rom[mapped_addr as usize])
+            }
+            Mapped::Memory(mapped_addr) => Some(self.character_ram[mapped_addr as usize]),
+            Mapped::Ram(_) | Mapped::Register | Mapped::Unmapped => None,
+        }
     }
 
-    pub fn ppu_map_write(&mut self, addr: u16, data: u8) -> Option<u8> {
-        self.mapper.ppu_map_write(addr).map(|mapped_addr| {
-            self.character_memory[mapped_addr as usize] = data;
-            self.character_memory[mapped_addr as usize]
-        })
+    /// Returns true if the write was handled by the cartridge.
+    pub fn ppu_map_write(&mut self, addr: u16, data: u8) -> bool {
+        match self.mapper.ppu_map_write(addr) {
+            Mapped::Memory(mapped_addr) => {
+                // writes to CHR-ROM are ignored
+                if let Some(byte) = self.character_ram.get_mut(mapped_addr as usize) {
+                    *byte = data;
+                }
+                true
+            }
+            Mapped::Register => true,
+            Mapped::Ram(_) | Mapped::Unmapped => false,
+        }
     }
 }
 
+/// Amount of `bank_size` banks needed to hold `size` bytes.
+fn banks(size: usize, bank_size: usize) -> Result<u8, CartridgeError> {
+    u8::try_from(size.div_ceil(bank_size))
+        .map_err(|_| CartridgeError::UnimplementedError(format!("ROM of {size} bytes")))
+}
+
 impl std::fmt::Display for Cartridge {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", String::from_utf8_lossy(&self.header.name))
     }
 }
+
+/// Builds an iNES ROM with `prg_16k` banks of PRG-ROM and `chr_8k`
+/// banks of CHR-ROM, all zeroes. `flags6` holds the low bits of byte 6,
+/// e.g. the mirroring and the battery.
+#[cfg(test)]
+pub(crate) fn ines_rom(mapper: u8, prg_16k: u8, chr_8k: u8, flags6: u8) -> Vec<u8> {
+    let mut rom = vec![0; 16];
+    rom[..4].copy_from_slice(b"NES\x1A");
+    rom[4] = prg_16k;
+    rom[5] = chr_8k;
+    rom[6] = (mapper << 4) | (flags6 & 0x0F);
+    rom[7] = mapper & 0xF0;
+    rom.resize(16 + prg_16k as usize * 16384 + chr_8k as usize * 8192, 0);
+    rom
+}
+
+#[test]
+fn test_rom_is_read_only() {
+    let mut rom = ines_rom(0, 1, 1, 0);
+    for (i, byte) in rom[16..].iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+
+    let mut cart = Cartridge::from_bytes(&rom).unwrap();
+    let before = cart.cpu_map_read(0x8001);
+
+    assert!(cart.cpu_map_write(0x8001, 0xFF));
+    assert_eq!(cart.cpu_map_read(0x8001), before);
+}
+
+#[test]
+fn test_bus_conflicts() {
+    let mut rom = ines_rom(3, 2, 4, 0);
+    // every byte of a CHR bank holds the number of that bank
+    for (bank, chr) in rom[16 + 32768..].chunks_mut(8192).enumerate() {
+        chr.fill(bank as u8);
+    }
+    // the ROM holds 0x02 at $8000
+    rom[16] = 0x02;
+
+    let mut cart = Cartridge::from_bytes(&rom).unwrap();
+
+    // 0x03 & 0x02 selects CHR bank 2
+    cart.cpu_map_write(0x8000, 0x03);
+    assert_eq!(cart.ppu_map_read(0x0000), Some(2));
+}
+
+#[test]
+fn test_character_ram() {
+    // no CHR-ROM
+    let rom = ines_rom(2, 2, 0, 0);
+
+    let mut cart = Cartridge::from_bytes(&rom).unwrap();
+
+    assert!(cart.ppu_map_write(0x1FFF, 0x42));
+    assert_eq!(cart.ppu_map_read(0x1FFF), Some(0x42));
+}
+
+#[test]
+fn test_battery_ram() {
+    // flag 1 of byte 6: the cartridge has a battery
+    let rom = ines_rom(0, 1, 1, 0x02);
+
+    let mut cart = Cartridge::from_bytes(&rom).unwrap();
+    assert!(cart.cpu_map_write(0x6000, 0x42));
+
+    let save = cart.battery_ram().unwrap().to_vec();
+    assert_eq!(save.len(), 8192);
+    assert_eq!(save[0], 0x42);
+
+    let mut cart = Cartridge::from_bytes(&rom).unwrap();
+    cart.load_battery_ram(&save).unwrap();
+    assert_eq!(cart.cpu_map_read(0x6000), Some(0x42));
+    assert!(cart.load_battery_ram(&save[1..]).is_err());
+}
+
+#[test]
+fn test_no_program_ram() {
+    // UxROM, CNROM, AxROM and GxROM boards leave $6000-$7FFF to open bus
+    for mapper in [2, 3, 7, 66] {
+        let mut cart = Cartridge::from_bytes(&ines_rom(mapper, 2, 1, 0)).unwrap();
+        assert!(!cart.cpu_map_write(0x6000, 0x42));
+        assert_eq!(cart.cpu_map_read(0x6000), None);
+    }
+
+    // unless a NES 2.0 header asks for 8 KB of PRG-RAM
+    for mapper in [2, 3] {
+        let mut rom = ines_rom(mapper, 2, 1, 0);
+        rom[7] |= 0x08;
+        rom[10] = 0x07;
+        let mut cart = Cartridge::from_bytes(&rom).unwrap();
+        assert!(cart.cpu_map_write(0x6000, 0x42));
+        assert_eq!(cart.cpu_map_read(0x6000), Some(0x42));
+    }
+}
@@ -1,12 +1,38 @@
 //! Module for the console's controllers.
+//!
+//! Input devices are plugged into one of the two controller ports, or
+//! into the Famicom's expansion port. The CPU talks to them through two
+//! registers:
+//! * writing $4016 sets the outputs of the ports, bit 0 being the
+//!   strobe of the controllers
+//! * reading $4016 or $4017 returns the bits 0 to 4 driven by the
+//!   devices, the other bits keep the last value seen on the data bus
+//!
+//! Light guns like the `Zapper` also watch the picture drawn by the PPU.
+
+mod zapper;
+
+use std::fmt::Debug;
 
 use bitflags::bitflags;
 
+use crate::screen::pixel::Pixel;
+use crate::screen::{NES_HEIGHT, NES_WIDTH};
+use crate::state::{StateError, StateReader, StateWriter};
+
+pub use zapper::Zapper;
+
 pub const CTRL_ADDR_START: u16 = 0x4016;
 pub const CTRL_ADDR_END: u16 = 0x4017;
 
+/// Bits of $4016 and $4017 driven by the input devices.
+pub const CTRL_DATA_MASK: u8 = 0x1F;
+
+/// Stands for an empty port in save states, see `InputDevice::state_id`.
+pub const NO_DEVICE_ID: u8 = 0;
+
 bitflags! {
-    #[derive(Copy, Clone, Debug)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
     pub struct Controller: u8 {
         const    RIGHT = 0b0000_0001;
         const     LEFT = 0b0000_0010;
@@ -18,3 +44,217 @@ bitflags! {
         const BUTTON_B = 0b1000_0000;
     }
 }
+
+impl Default for Controller {
+    fn default() -> Self {
+        Controller::empty()
+    }
+}
+
+/// Where an input device is plugged.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Port {
+    /// Controller port 1, read through $4016
+    One,
+    /// Controller port 2, read through $4017
+    Two,
+    /// Famicom expansion port, read through both $4016 and $4017
+    Expansion,
+}
+
+impl Port {
+    pub const ALL: [Port; 3] = [Port::One, Port::Two, Port::Expansion];
+
+    /// Address of the register the device is read through, `None` for
+    /// the expansion port which uses both.
+    pub fn addr(self) -> Option<u16> {
+        match self {
+            Port::One => Some(CTRL_ADDR_START),
+            Port::Two => Some(CTRL_ADDR_END),
+            Port::Expansion => None,
+        }
+    }
+
+    /// Returns true if the device is read through `addr`.
+    pub fn reads(self, addr: u16) -> bool {
+        self.addr().is_none_or(|port_addr| port_addr == addr)
+    }
+}
+
+/// The picture as seen by light guns when they are read.
+#[derive(Clone, Copy, Debug)]
+pub struct Beam<'a> {
+    /// The frame being drawn. The rows the beam hasn't reached yet
+    /// still hold an older frame.
+    pub frame: &'a [[Pixel; NES_WIDTH]; NES_HEIGHT],
+    /// Scanline being drawn, from -1 (pre-render) to the last scanline
+    /// of the region
+    pub scanline: i16,
+    /// Dot within the scanline, the pixel at column `dot - 1` is drawn
+    pub dot: i16,
+}
+
+/// Input Device trait.
+///
+/// Allows plugging any peripheral into the console's ports.
+pub trait InputDevice: Debug + InputDeviceClone {
+    /// Called on writes to $4016. Bit 0 is the strobe of the controller
+    /// ports, bits 0 to 2 are the outputs of the expansion port.
+    fn write(&mut self, data: u8);
+
+    /// Called on reads of `addr`, $4016 or $4017. Only bits 0 to 4 are
+    /// driven by the device, see `CTRL_DATA_MASK`.
+    ///
+    /// Devices on the controller ports are only read through the
+    /// address of their port.
+    fn read(&mut self, addr: u16) -> u8;
+
+    /// Same as `read`, without changing the state of the device.
+    fn peek(&self, addr: u16) -> u8;
+
+    /// Identifies the kind of device in save states, which can only be
+    /// loaded with the same kinds of devices plugged in. Must not be
+    /// `NO_DEVICE_ID`.
+    fn state_id(&self) -> u8;
+
+    /// Holds the buttons chosen by the player, for devices that have
+    /// some. Called with the buttons of `Nes::mut_controllers` before
+    /// the device is read or written.
+    fn set_buttons(&mut self, _buttons: Controller) {}
+
+    /// Points light guns at the pixel `(x, y)` of the screen, or away
+    /// from it when `aim` is `None`, and holds their trigger.
+    fn set_aim(&mut self, _aim: Option<(usize, usize)>, _trigger: bool) {}
+
+    /// Lets light guns look at the picture, called before each read.
+    fn sense_light(&mut self, _beam: &Beam) {}
+
+    /// Writes the state of the device to a save state.
+    fn save_state(&self, _state: &mut StateWriter) {}
+
+    /// Restores the state of the device from a save state, reading it
+    /// in the same order it was written.
+    fn load_state(&mut self, _state: &mut StateReader) -> Result<(), StateError> {
+        Ok(())
+    }
+}
+
+/// Allows cloning boxed input devices, which is needed to clone the console.
+///
+/// Automatically implemented for every input device that implements `Clone`.
+pub trait InputDeviceClone {
+    fn clone_box(&self) -> Box<dyn InputDevice>;
+}
+
+impl<T> InputDeviceClone for T
+where
+    T: 'static + InputDevice + Clone,
+{
+    fn clone_box(&self) -> Box<dyn InputDevice> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn InputDevice> {
+    fn clone(&self) -> Box<dyn InputDevice> {
+        self.clone_box()
+    }
+}
+
+/// The standard controller, with a D-pad and four buttons.
+///
+/// While the strobe is high, the controller keeps latching the buttons.
+/// Once it goes low, each read returns the next button on bit 0, in the
+/// order A, B, Select, Start, Up, Down, Left, Right. Later reads return 1.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StandardController {
+    buttons: Controller,
+    strobe: bool,
+    /// Buttons not read yet, the next one in bit 0
+    shift: u8,
+}
+
+impl StandardController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The buttons in the order they are read, A first.
+    fn report(&self) -> u8 {
+        [
+            Controller::BUTTON_A,
+            Controller::BUTTON_B,
+            Controller::SELECT,
+            Controller::START,
+            Controller::UP,
+            Controller::DOWN,
+            Controller::LEFT,
+            Controller::RIGHT,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |report, (i, &button)| {
+            report | (u8::from(self.buttons.contains(button)) << i)
+        })
+    }
+}
+
+impl InputDevice for StandardController {
+    fn state_id(&self) -> u8 {
+        1
+    }
+
+    fn write(&mut self, data: u8) {
+        self.strobe = data & 0x01 != 0;
+        if self.strobe {
+            self.shift = self.report();
+        }
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        let data = self.peek(addr);
+        if !self.strobe {
+            // the register is filled with ones as it shifts
+            self.shift = (self.shift >> 1) | 0x80;
+        }
+        data
+    }
+
+    fn peek(&self, _addr: u16) -> u8 {
+        if self.strobe {
+            // only the A button is read while the buttons are latched
+            self.report() & 0x01
+        } else {
+            self.shift & 0x01
+        }
+    }
+
+    fn set_buttons(&mut self, buttons: Controller) {
+        self.buttons = buttons;
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.strobe);
+        state.write_u8(self.shift);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.strobe = state.read_bool()?;
+        self.shift = state.read_u8()?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_standard_controller() {
+    let mut controller = StandardController::new();
+    controller.set_buttons(Controller::BUTTON_A | Controller::START | Controller::RIGHT);
+
+    controller.write(1);
+    assert_eq!(controller.read(CTRL_ADDR_START), 1);
+    assert_eq!(controller.read(CTRL_ADDR_START), 1);
+    controller.write(0);
+
+    let bits: Vec<u8> = (0..10).map(|_| controller.read(CTRL_ADDR_START)).collect();
+    assert_eq!(bits, [1, 0, 0, 1, 0, 0, 0, 1, 1, 1]);
+}
@@ -0,0 +1,124 @@
+//! The Zapper light gun.
+
+use itertools::Itertools;
+
+use super::{Beam, InputDevice};
+use crate::screen::{NES_HEIGHT, NES_WIDTH};
+
+/// Bit of the report cleared while the sensor sees light.
+const LIGHT_BIT: u8 = 0x08;
+/// Bit of the report set while the trigger is pulled.
+const TRIGGER_BIT: u8 = 0x10;
+
+/// Pixels around the aim seen by the sensor, in each direction.
+const SENSOR_RADIUS: usize = 2;
+/// Scanlines during which a pixel stays bright enough to be sensed
+/// after the beam drew it.
+const SENSOR_PERSISTENCE: usize = 20;
+/// Luma from which a pixel is bright enough to be sensed.
+const SENSOR_THRESHOLD: u32 = 0xA0;
+
+/// The Zapper, usually plugged into the second controller port.
+///
+/// Games find where it points by lighting parts of the screen, e.g. a
+/// white box over each target, and checking whether the sensor sees the
+/// light. Bit 3 of the report is cleared while the sensor sees a bright
+/// pixel the beam drew recently, bit 4 is set while the trigger is
+/// pulled.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Zapper {
+    /// Pixel of the screen pointed at, as `(x, y)`
+    aim: Option<(usize, usize)>,
+    trigger: bool,
+    light: bool,
+}
+
+impl Zapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn sees_light(&self, beam: &Beam) -> bool {
+        let Some((x, y)) = self.aim else {
+            return false;
+        };
+        if x >= NES_WIDTH || y >= NES_HEIGHT || beam.scanline < 0 {
+            return false;
+        }
+
+        let (scanline, dot) = (beam.scanline as usize, beam.dot as usize);
+        let rows = y.saturating_sub(SENSOR_RADIUS)..=(y + SENSOR_RADIUS).min(NES_HEIGHT - 1);
+        let cols = x.saturating_sub(SENSOR_RADIUS)..=(x + SENSOR_RADIUS).min(NES_WIDTH - 1);
+        rows.cartesian_product(cols).any(|(row, col)| {
+            let drawn = row < scanline || (row == scanline && col + 1 < dot);
+            let pixel = beam.frame[row][col];
+            let luma = (77 * pixel.r as u32 + 150 * pixel.g as u32 + 29 * pixel.b as u32) >> 8;
+            drawn && scanline - row < SENSOR_PERSISTENCE && luma >= SENSOR_THRESHOLD
+        })
+    }
+}
+
+impl InputDevice for Zapper {
+    fn state_id(&self) -> u8 {
+        2
+    }
+
+    fn write(&mut self, _data: u8) {}
+
+    fn read(&mut self, addr: u16) -> u8 {
+        self.peek(addr)
+    }
+
+    fn peek(&self, _addr: u16) -> u8 {
+        let mut data = 0;
+        if !self.light {
+            data |= LIGHT_BIT;
+        }
+        if self.trigger {
+            data |= TRIGGER_BIT;
+        }
+        data
+    }
+
+    fn set_aim(&mut self, aim: Option<(usize, usize)>, trigger: bool) {
+        self.aim = aim;
+        self.trigger = trigger;
+    }
+
+    fn sense_light(&mut self, beam: &Beam) {
+        self.light = self.sees_light(beam);
+    }
+}
+
+#[test]
+fn test_zapper() {
+    use crate::controller::CTRL_ADDR_END;
+    use crate::screen::pixel::Pixel;
+
+    let mut frame = Box::new([[Pixel::default(); NES_WIDTH]; NES_HEIGHT]);
+    // a white box, like the ones drawn over the targets
+    for row in frame.iter_mut().skip(100).take(16) {
+        row[120..136].fill(Pixel::new(236, 238, 236));
+    }
+    let beam = |scanline, dot| Beam {
+        frame: &frame,
+        scanline,
+        dot,
+    };
+
+    let mut zapper = Zapper::new();
+    zapper.set_aim(Some((128, 108)), true);
+    zapper.sense_light(&beam(110, 50));
+    assert_eq!(zapper.read(CTRL_ADDR_END), TRIGGER_BIT);
+
+    // the beam hasn't drawn the box yet, or drew it too long ago
+    zapper.sense_light(&beam(90, 50));
+    assert_eq!(zapper.read(CTRL_ADDR_END), LIGHT_BIT | TRIGGER_BIT);
+    zapper.sense_light(&beam(200, 50));
+    assert_eq!(zapper.read(CTRL_ADDR_END), LIGHT_BIT | TRIGGER_BIT);
+
+    // pointed at the dark part of the screen
+    zapper.set_aim(Some((20, 108)), false);
+    zapper.sense_light(&beam(110, 50));
+    assert_eq!(zapper.read(CTRL_ADDR_END), LIGHT_BIT);
+}
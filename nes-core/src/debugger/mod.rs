@@ -0,0 +1,420 @@
+//! Debugger.
+//!
+//! A `Debugger` runs the console one clock cycle at a time, stopping
+//! when a breakpoint is hit or when a step is complete. Breakpoints
+//! can watch the execution, reads and writes of an address range, a
+//! PPU position, interrupts and opcodes.
+//!
+//! The debugger is kept outside of the console, so the same console
+//! can be run at full speed with `Nes::next_frame` and inspected with
+//! `Debugger::step` in turns.
+
+use std::ops::RangeInclusive;
+
+use crate::system::System;
+use crate::Nes;
+
+/// Interrupt taken by the CPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    Nmi,
+    Irq,
+}
+
+/// Condition that stops the emulation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// The CPU is about to execute an instruction in the range
+    Execute(RangeInclusive<u16>),
+    /// The CPU reads an address in the range. Opcode and operand
+    /// fetches are reads too.
+    Read(RangeInclusive<u16>),
+    /// The CPU writes an address in the range
+    Write(RangeInclusive<u16>),
+    /// The PPU reaches a dot
+    Ppu { scanline: i16, dot: i16 },
+    /// The CPU takes a non-maskable interrupt
+    Nmi,
+    /// The CPU takes an interrupt request
+    Irq,
+    /// The CPU is about to execute an opcode
+    Opcode(u8),
+}
+
+/// Identifies a breakpoint added to a `Debugger`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BreakpointId(u32);
+
+/// How far `Debugger::step` runs the console.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Until the next instruction starts
+    Instruction,
+    /// Until the PPU starts another scanline
+    Scanline,
+    /// Until the PPU completes a frame
+    Frame,
+}
+
+/// What made a breakpoint stop the emulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The instruction at `pc` is about to be executed. It is
+    /// executed by the next step.
+    Execute {
+        pc: u16,
+        opcode: u8,
+    },
+    Read {
+        addr: u16,
+        value: u8,
+    },
+    Write {
+        addr: u16,
+        value: u8,
+    },
+    Ppu {
+        scanline: i16,
+        dot: i16,
+    },
+    Interrupt(Interrupt),
+}
+
+/// Why `Debugger::step` returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint {
+        id: BreakpointId,
+        event: Event,
+    },
+    StepComplete,
+    /// The CPU was halted by a JAM opcode. Only stepping by scanline
+    /// or by frame still runs the PPU.
+    Jammed,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    id: BreakpointId,
+    breakpoint: Breakpoint,
+    enabled: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    breakpoints: Vec<Entry>,
+    next_id: u32,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an enabled breakpoint.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> BreakpointId {
+        let id = BreakpointId(self.next_id);
+        self.next_id += 1;
+        self.breakpoints.push(Entry {
+            id,
+            breakpoint,
+            enabled: true,
+        });
+        id
+    }
+
+    /// Returns the removed breakpoint, or `None` if `id` is unknown.
+    pub fn remove_breakpoint(&mut self, id: BreakpointId) -> Option<Breakpoint> {
+        let index = self.breakpoints.iter().position(|entry| entry.id == id)?;
+        Some(self.breakpoints.remove(index).breakpoint)
+    }
+
+    /// Enables or disables a breakpoint without removing it.
+    /// Returns false if `id` is unknown.
+    pub fn set_enabled(&mut self, id: BreakpointId, enabled: bool) -> bool {
+        match self.breakpoints.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => {
+                entry.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Every breakpoint, in the order they were added, and whether
+    /// it's enabled.
+    pub fn breakpoints(&self) -> impl Iterator<Item = (BreakpointId, &Breakpoint, bool)> {
+        self.breakpoints
+            .iter()
+            .map(|entry| (entry.id, &entry.breakpoint, entry.enabled))
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Runs `nes` until the step is complete or a breakpoint is hit.
+    ///
+    /// Execute and opcode breakpoints stop the emulation before the
+    /// instruction is executed, so stepping again from there executes
+    /// it. Other breakpoints stop it after the clock cycle that
+    /// triggered them, which may be in the middle of an instruction.
+    pub fn step(&mut self, nes: &mut Nes, step: Step) -> StopReason {
+        let system = &mut nes.system;
+
+        // recording every access is slow, so it's only done when needed
+        let watch_memory = self
+            .enabled()
+            .any(|breakpoint| matches!(breakpoint, Breakpoint::Read(_) | Breakpoint::Write(_)));
+        system.record_accesses(watch_memory);
+        let reason = self.run(system, step);
+        system.record_accesses(false);
+
+        reason
+    }
+
+    fn run(&self, system: &mut System, step: Step) -> StopReason {
+        let jammed = system.cpu_jammed();
+        let (start_scanline, _) = system.ppu_position();
+        let mut first = true;
+
+        loop {
+            if jammed && step == Step::Instruction {
+                return StopReason::Jammed;
+            }
+
+            if system.instruction_starts() {
+                if step == Step::Instruction && !first {
+                    return StopReason::StepComplete;
+                }
+                // skipped on the first cycle, to resume from the breakpoint
+                if !first {
+                    if let Some(reason) = self.check_execute(system) {
+                        return reason;
+                    }
+                }
+            }
+            first = false;
+
+            system.clock();
+            // before checking the breakpoints, so a frame completed on the
+            // cycle a breakpoint stops at is still shown and recorded
+            let frame_complete = system.screen_ready();
+
+            if let Some(reason) = self.check_cycle(system) {
+                return reason;
+            }
+            if !jammed && system.cpu_jammed() {
+                return StopReason::Jammed;
+            }
+
+            let complete = match step {
+                Step::Instruction => false,
+                Step::Scanline => system.ppu_position().0 != start_scanline,
+                Step::Frame => frame_complete,
+            };
+            if complete {
+                return StopReason::StepComplete;
+            }
+        }
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.breakpoints
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| &entry.breakpoint)
+    }
+
+    /// Returns the first enabled breakpoint triggered by `event`.
+    fn hit(&self, event: Event) -> Option<StopReason> {
+        let entry = self.breakpoints.iter().find(|entry| {
+            entry.enabled
+                && match (&entry.breakpoint, event) {
+                    (Breakpoint::Execute(range), Event::Execute { pc, .. }) => range.contains(&pc),
+                    (Breakpoint::Opcode(expected), Event::Execute { opcode, .. }) => {
+                        *expected == opcode
+                    }
+                    (Breakpoint::Read(range), Event::Read { addr, .. })
+                    | (Breakpoint::Write(range), Event::Write { addr, .. }) => {
+                        range.contains(&addr)
+                    }
+                    (
+                        Breakpoint::Ppu { scanline, dot },
+                        Event::Ppu {
+                            scanline: s,
+                            dot: d,
+                        },
+                    ) => (*scanline, *dot) == (s, d),
+                    (Breakpoint::Nmi, Event::Interrupt(Interrupt::Nmi))
+                    | (Breakpoint::Irq, Event::Interrupt(Interrupt::Irq)) => true,
+                    _ => false,
+                }
+        })?;
+
+        Some(StopReason::Breakpoint {
+            id: entry.id,
+            event,
+        })
+    }
+
+    /// Checks the instruction about to be executed.
+    fn check_execute(&self, system: &System) -> Option<StopReason> {
+        let pc = system.pc();
+        self.hit(Event::Execute {
+            pc,
+            opcode: system.peek(pc),
+        })
+    }
+
+    /// Checks what happened during the last clock cycle.
+    fn check_cycle(&self, system: &mut System) -> Option<StopReason> {
+        for access in system.take_accesses() {
+            let event = if access.write {
+                Event::Write {
+                    addr: access.addr,
+                    value: access.value,
+                }
+            } else {
+                Event::Read {
+                    addr: access.addr,
+                    value: access.value,
+                }
+            };
+            if let Some(reason) = self.hit(event) {
+                return Some(reason);
+            }
+        }
+
+        if let Some(interrupt) = system.interrupt() {
+            if let Some(reason) = self.hit(Event::Interrupt(interrupt)) {
+                return Some(reason);
+            }
+        }
+
+        let (scanline, dot) = system.ppu_position();
+        self.hit(Event::Ppu { scanline, dot })
+    }
+}
+
+#[test]
+fn test_debugger() {
+    use crate::cartridge::Cartridge;
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    let mut debugger = Debugger::new();
+
+    // the first step runs the reset sequence, up to the first instruction
+    let reset = nes.system.pc();
+    for _ in 0..2 {
+        assert_eq!(
+            debugger.step(&mut nes, Step::Instruction),
+            StopReason::StepComplete
+        );
+    }
+    assert_ne!(nes.system.pc(), reset);
+
+    // the game enables the NMI after a few frames
+    let nmi = debugger.add_breakpoint(Breakpoint::Nmi);
+    let reason = (0..5)
+        .map(|_| debugger.step(&mut nes, Step::Frame))
+        .find(|reason| *reason != StopReason::StepComplete)
+        .unwrap();
+    assert_eq!(
+        reason,
+        StopReason::Breakpoint {
+            id: nmi,
+            event: Event::Interrupt(Interrupt::Nmi)
+        }
+    );
+    let handler = nes.system.pc();
+    debugger.set_enabled(nmi, false);
+
+    // stops again on the first instruction of the handler
+    let execute = debugger.add_breakpoint(Breakpoint::Execute(handler..=handler));
+    let reason = debugger.step(&mut nes, Step::Frame);
+    assert!(
+        matches!(reason, StopReason::Breakpoint { id, event: Event::Execute { pc, .. } } if id == execute && pc == handler)
+    );
+    assert_eq!(
+        debugger.step(&mut nes, Step::Instruction),
+        StopReason::StepComplete
+    );
+    assert_ne!(nes.system.pc(), handler);
+
+    let write = debugger.add_breakpoint(Breakpoint::Write(0x2000..=0x2007));
+    let reason = debugger.step(&mut nes, Step::Frame);
+    assert!(
+        matches!(reason, StopReason::Breakpoint { id, event: Event::Write { addr: 0x2000..=0x2007, .. } } if id == write)
+    );
+
+    debugger.clear_breakpoints();
+    assert_eq!(
+        debugger.step(&mut nes, Step::Frame),
+        StopReason::StepComplete
+    );
+    let ppu = debugger.add_breakpoint(Breakpoint::Ppu {
+        scanline: 100,
+        dot: 5,
+    });
+    let reason = debugger.step(&mut nes, Step::Frame);
+    assert_eq!(
+        reason,
+        StopReason::Breakpoint {
+            id: ppu,
+            event: Event::Ppu {
+                scanline: 100,
+                dot: 5
+            }
+        }
+    );
+    assert_eq!(
+        debugger.remove_breakpoint(ppu),
+        Some(Breakpoint::Ppu {
+            scanline: 100,
+            dot: 5
+        })
+    );
+    assert_eq!(
+        debugger.step(&mut nes, Step::Scanline),
+        StopReason::StepComplete
+    );
+    assert_eq!(nes.system.ppu_position(), (101, 0));
+}
+
+#[test]
+fn test_debugger_frame_breakpoint() {
+    use crate::cartridge::Cartridge;
+
+    let cartridge = || Cartridge::from_file("test_data/roms/nestest.nes").unwrap();
+    let mut nes = Nes::new(cartridge());
+    let mut expected = Nes::new(cartridge());
+    let mut debugger = Debugger::new();
+
+    for _ in 0..3 {
+        debugger.step(&mut nes, Step::Frame);
+        expected.next_frame();
+    }
+
+    // the PPU completes the frame on the dot the breakpoint stops at
+    let ppu = debugger.add_breakpoint(Breakpoint::Ppu {
+        scanline: -1,
+        dot: 0,
+    });
+    assert!(matches!(
+        debugger.step(&mut nes, Step::Frame),
+        StopReason::Breakpoint { id, .. } if id == ppu
+    ));
+    expected.next_frame();
+    assert_eq!(nes.screen().crc32(), expected.screen().crc32());
+
+    // the next step runs a whole frame
+    debugger.remove_breakpoint(ppu);
+    assert_eq!(
+        debugger.step(&mut nes, Step::Frame),
+        StopReason::StepComplete
+    );
+    expected.next_frame();
+    assert_eq!(nes.system.cpu_cycles(), expected.system.cpu_cycles());
+    assert_eq!(nes.screen().crc32(), expected.screen().crc32());
+}
@@ -0,0 +1,357 @@
+//! 6502 disassembler.
+//!
+//! Decodes machine code into instructions, either from a slice of bytes
+//! or from the memory of a running console with `Nes::disassemble`.
+//!
+//! Unofficial opcodes are decoded using the mnemonics of ca65's 6502X
+//! mode, so the output of `to_ca65` can be assembled again with
+//! `ca65 --cpu 6502X`. Opcodes that ca65 would assemble into another
+//! opcode, like $EB (`SBC #imm`) or the extra NOPs, are written as
+//! `.byte` so the same bytes come out.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+
+use crate::system::cpu::addressing::AddrType;
+use crate::system::cpu::instructions::Instruction as CpuInstruction;
+
+/// How an instruction finds its operand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressingMode {
+    /// No operand
+    Implied,
+    /// Operates on the accumulator, e.g. `ASL A`
+    Accumulator,
+    /// `#$nn`
+    Immediate,
+    /// `$nn`
+    ZeroPage,
+    /// `$nn,X`
+    ZeroPageX,
+    /// `$nn,Y`
+    ZeroPageY,
+    /// `$nnnn`
+    Absolute,
+    /// `$nnnn,X`
+    AbsoluteX,
+    /// `$nnnn,Y`
+    AbsoluteY,
+    /// `($nnnn)`, only used by JMP
+    Indirect,
+    /// `($nn,X)`
+    IndirectX,
+    /// `($nn),Y`
+    IndirectY,
+    /// Signed offset from the next instruction, used by branches
+    Relative,
+}
+
+impl AddressingMode {
+    /// Length in bytes of an instruction using this addressing mode,
+    /// including its opcode.
+    pub fn instruction_len(self) -> u8 {
+        use AddressingMode::*;
+
+        match self {
+            Implied | Accumulator => 1,
+            Absolute | AbsoluteX | AbsoluteY | Indirect => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// A decoded instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// Address of the opcode
+    pub addr: u16,
+    pub opcode: u8,
+    /// Name of the instruction, e.g. "LDA"
+    pub mnemonic: &'static str,
+    pub mode: AddressingMode,
+    /// 8 or 16-bit operand, as found after the opcode. For branches,
+    /// this is the raw offset; see `target`.
+    pub operand: Option<u16>,
+    /// Length in bytes, including the opcode
+    pub len: u8,
+    /// Address the instruction jumps or branches to, if known without
+    /// running it. Indirect jumps have no target.
+    pub target: Option<u16>,
+    /// False for opcodes that are not part of the 6502's standard
+    pub official: bool,
+}
+
+impl Instruction {
+    /// Address of the instruction that follows this one.
+    pub fn next_addr(&self) -> u16 {
+        self.addr.wrapping_add(self.len as u16)
+    }
+
+    /// Returns true if ca65 assembles the mnemonic and addressing mode
+    /// of the instruction back into its opcode. When several opcodes
+    /// share them, ca65 picks the official one, or else the lowest one.
+    fn assembles_back(&self) -> bool {
+        let opcode = (0..=0xFF)
+            .map(CpuInstruction::lookup)
+            .filter(|i| i.mnemonic() == self.mnemonic && addressing_mode(i) == self.mode)
+            .min_by_key(|i| (!i.official(), i._opcode))
+            .map(|i| i._opcode);
+        opcode == Some(self.opcode)
+    }
+
+    /// The bytes of the instruction as a ca65 `.byte` directive.
+    fn write_bytes(&self, f: &mut impl Write) -> fmt::Result {
+        write!(f, ".byte ${:02X}", self.opcode)?;
+        let operand = self.operand.unwrap_or(0).to_le_bytes();
+        for byte in &operand[..self.len as usize - 1] {
+            write!(f, ", ${byte:02X}")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the instruction in ca65 syntax, using `label` to name
+    /// the target of jumps and branches.
+    fn write_ca65(&self, f: &mut impl Write, label: Option<&str>) -> fmt::Result {
+        use AddressingMode::*;
+
+        let operand = self.operand.unwrap_or(0);
+        write!(f, "{}", self.mnemonic)?;
+
+        if let (Some(label), Some(_)) = (label, self.target) {
+            return match self.mode {
+                Relative | Absolute => write!(f, " {label}"),
+                _ => unreachable!("only branches and absolute jumps have targets"),
+            };
+        }
+
+        // ca65 uses zero page addressing whenever the address fits in a
+        // byte, unless the operand is prefixed with "a:"
+        let abs = if operand < 0x100 { "a:" } else { "" };
+
+        match self.mode {
+            Implied => Ok(()),
+            Accumulator => write!(f, " A"),
+            Immediate => write!(f, " #${operand:02X}"),
+            ZeroPage => write!(f, " ${operand:02X}"),
+            ZeroPageX => write!(f, " ${operand:02X},X"),
+            ZeroPageY => write!(f, " ${operand:02X},Y"),
+            Absolute => write!(f, " {abs}${operand:04X}"),
+            AbsoluteX => write!(f, " {abs}${operand:04X},X"),
+            AbsoluteY => write!(f, " {abs}${operand:04X},Y"),
+            Indirect => write!(f, " (${operand:04X})"),
+            IndirectX => write!(f, " (${operand:02X},X)"),
+            IndirectY => write!(f, " (${operand:02X}),Y"),
+            Relative => write!(f, " ${:04X}", self.target.unwrap_or_default()),
+        }
+    }
+}
+
+/// Formats the instruction in ca65 syntax, e.g. `LDA ($80),Y`.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_ca65(f, None)
+    }
+}
+
+/// Decodes the instruction at the start of `bytes`, which are located
+/// at address `addr`.
+///
+/// Returns `None` if `bytes` ends before the instruction does.
+pub fn decode(bytes: &[u8], addr: u16) -> Option<Instruction> {
+    let opcode = *bytes.first()?;
+    let cpu_instruction = CpuInstruction::lookup(opcode);
+    let mnemonic = cpu_instruction.mnemonic();
+    let mode = addressing_mode(cpu_instruction);
+
+    let len = mode.instruction_len();
+    let operand = match len {
+        2 => Some(*bytes.get(1)? as u16),
+        3 => Some(u16::from_le_bytes([*bytes.get(1)?, *bytes.get(2)?])),
+        _ => None,
+    };
+
+    let next = addr.wrapping_add(len as u16);
+    let target = match (mode, mnemonic) {
+        (AddressingMode::Relative, _) => {
+            operand.map(|offset| next.wrapping_add(offset as u8 as i8 as u16))
+        }
+        (AddressingMode::Absolute, "JMP" | "JSR") => operand,
+        _ => None,
+    };
+
+    Some(Instruction {
+        addr,
+        opcode,
+        mnemonic,
+        mode,
+        operand,
+        len,
+        target,
+        official: cpu_instruction.official(),
+    })
+}
+
+fn addressing_mode(cpu_instruction: &CpuInstruction) -> AddressingMode {
+    match cpu_instruction.addrmode.typ {
+        AddrType::Imp => match cpu_instruction.mnemonic() {
+            "ASL" | "LSR" | "ROL" | "ROR" => AddressingMode::Accumulator,
+            _ => AddressingMode::Implied,
+        },
+        // BRK skips a byte, but it's not an operand
+        AddrType::Imm if cpu_instruction.mnemonic() == "BRK" => AddressingMode::Implied,
+        AddrType::Imm => AddressingMode::Immediate,
+        AddrType::Zp0 => AddressingMode::ZeroPage,
+        AddrType::Zpx => AddressingMode::ZeroPageX,
+        AddrType::Zpy => AddressingMode::ZeroPageY,
+        AddrType::Abs => AddressingMode::Absolute,
+        AddrType::Abx => AddressingMode::AbsoluteX,
+        AddrType::Aby => AddressingMode::AbsoluteY,
+        AddrType::Ind => AddressingMode::Indirect,
+        AddrType::Izx => AddressingMode::IndirectX,
+        AddrType::Izy => AddressingMode::IndirectY,
+        AddrType::Rel => AddressingMode::Relative,
+    }
+}
+
+/// Decodes every instruction in `bytes`, which start at address `addr`.
+///
+/// Decoding stops at the first instruction that doesn't fit in `bytes`.
+pub fn disassemble(bytes: &[u8], addr: u16) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while let Some(instruction) = bytes
+        .get(offset..)
+        .and_then(|b| decode(b, addr.wrapping_add(offset as u16)))
+    {
+        offset += instruction.len as usize;
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+/// Formats instructions as a ca65 source file.
+///
+/// Jumps and branches to one of the instructions use a label instead
+/// of an address. Each line ends with a comment holding the address
+/// of the instruction, followed by the instruction itself when it is
+/// written as `.byte`.
+pub fn to_ca65(instructions: &[Instruction]) -> String {
+    let addrs: BTreeSet<u16> = instructions.iter().map(|i| i.addr).collect();
+    let label = |addr: u16| format!("L{addr:04X}");
+    let labelled: BTreeSet<u16> = instructions
+        .iter()
+        .filter_map(|i| i.target)
+        .filter(|target| addrs.contains(target))
+        .collect();
+
+    let mut source = String::from(".setcpu \"6502X\"\n");
+    let mut next = None;
+
+    for instruction in instructions {
+        if next != Some(instruction.addr) {
+            let _ = writeln!(source, "\n.org ${:04X}", instruction.addr);
+        }
+        next = Some(instruction.next_addr());
+
+        if labelled.contains(&instruction.addr) {
+            let _ = writeln!(source, "{}:", label(instruction.addr));
+        }
+
+        let target_label = instruction
+            .target
+            .filter(|target| labelled.contains(target))
+            .map(label);
+        let mut text = String::new();
+        if instruction.assembles_back() {
+            let _ = instruction.write_ca65(&mut text, target_label.as_deref());
+            let _ = writeln!(source, "    {text:<24}; {:04X}", instruction.addr);
+        } else {
+            let _ = instruction.write_bytes(&mut text);
+            let _ = writeln!(
+                source,
+                "    {text:<24}; {:04X} {instruction}",
+                instruction.addr
+            );
+        }
+    }
+
+    source
+}
+
+#[test]
+fn test_disassemble() {
+    let bytes = [
+        0xA2, 0x00, // LDX #$00
+        0xBD, 0x10, 0x00, // LDA a:$0010,X
+        0xB3, 0x80, // LAX ($80),Y
+        0xD0, 0xF7, // BNE $C000
+        0x0A, // ASL A
+        0x6C, 0xFC, 0xFF, // JMP ($FFFC)
+        0xAD, // truncated LDA
+    ];
+    let instructions = disassemble(&bytes, 0xC000);
+
+    let text: Vec<_> = instructions.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        text,
+        [
+            "LDX #$00",
+            "LDA a:$0010,X",
+            "LAX ($80),Y",
+            "BNE $C000",
+            "ASL A",
+            "JMP ($FFFC)"
+        ]
+    );
+
+    assert!(!instructions[2].official);
+    assert_eq!(instructions[3].target, Some(0xC000));
+    assert_eq!(instructions[5].len, 3);
+    assert!(to_ca65(&instructions).contains("LC000:\n    LDX #$00"));
+    assert!(to_ca65(&instructions).contains("    BNE LC000"));
+}
+
+#[test]
+fn test_ca65_round_trip() {
+    use std::collections::HashMap;
+
+    // text of each opcode assembled by ca65, with the bytes it gives
+    let mut assembled = HashMap::new();
+    for opcode in 0..=0xFF {
+        let bytes = [opcode, 0x34, 0x12];
+        let instruction = decode(&bytes, 0x8000).unwrap();
+        let source = to_ca65(&[instruction]);
+        let line = source.lines().last().unwrap();
+        let text = line.split(';').next().unwrap().trim();
+
+        match text.strip_prefix(".byte ") {
+            Some(list) => {
+                let written: Vec<u8> = list
+                    .split(", ")
+                    .map(|byte| u8::from_str_radix(&byte[1..], 16).unwrap())
+                    .collect();
+                assert_eq!(written, bytes[..instruction.len as usize]);
+            }
+            None => {
+                let previous = assembled.insert(text.to_string(), opcode);
+                assert_eq!(previous, None, "{text} is written for several opcodes");
+            }
+        }
+    }
+
+    // the opcodes ca65 picks when several share a mnemonic and mode
+    for (text, opcode) in [
+        ("SBC #$34", 0xE9),
+        ("NOP", 0xEA),
+        ("NOP #$34", 0x80),
+        ("NOP $34", 0x04),
+        ("NOP $34,X", 0x14),
+        ("NOP $1234,X", 0x1C),
+        ("ANC #$34", 0x0B),
+        ("JAM", 0x02),
+    ] {
+        assert_eq!(assembled.get(text), Some(&opcode));
+    }
+}
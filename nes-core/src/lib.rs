@@ -1,12 +1,27 @@
 pub mod cartridge;
 pub mod controller;
+pub mod debugger;
+pub mod disasm;
+pub mod movie;
+pub mod recorder;
+pub mod region;
+pub mod rewind;
 pub mod screen;
+pub mod state;
+pub mod trace;
 
 pub(crate) mod system;
+pub(crate) mod util;
 
-use crate::cartridge::Cartridge;
-use crate::controller::Controller;
-use crate::screen::NesScreen;
+use std::ops::RangeInclusive;
+
+use crate::cartridge::{Cartridge, CartridgeError, CartridgeMetadata};
+use crate::controller::{Controller, InputDevice, Port};
+use crate::recorder::Recorder;
+use crate::region::Region;
+use crate::screen::{NametableScreen, NesScreen, PaletteScreen, PatternTableScreen, SpriteScreen};
+use crate::state::StateError;
+use crate::trace::{CpuTrace, Tracer};
 use system::System;
 
 #[derive(Clone, Debug, Default)]
@@ -21,10 +36,28 @@ impl Nes {
         }
     }
 
+    /// Creates an independent copy of the console.
+    ///
+    /// Same as `clone`: the copy has its own RAM, cartridge RAM and
+    /// mapper registers, only the ROM data is shared.
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
     pub fn screen(&self) -> &NesScreen {
         self.system.screen()
     }
 
+    pub(crate) fn mut_screen(&mut self) -> &mut NesScreen {
+        self.system.mut_screen()
+    }
+
+    /// MD5 of the ROM, excluding the header, which identifies the game
+    /// in FM2 movies.
+    pub(crate) fn rom_md5(&self) -> [u8; 16] {
+        self.system.rom_md5()
+    }
+
     pub fn next_frame(&mut self) -> &NesScreen {
         self.system.next_frame()
     }
@@ -37,6 +70,228 @@ impl Nes {
         self.system.mut_controllers()
     }
 
+    /// The device plugged into `port`, if any.
+    pub fn input_device(&self, port: Port) -> Option<&dyn InputDevice> {
+        self.system.input_device(port)
+    }
+
+    pub fn mut_input_device(&mut self, port: Port) -> Option<&mut dyn InputDevice> {
+        self.system.mut_input_device(port)
+    }
+
+    /// Plugs `device` into `port`, or unplugs the current device when
+    /// `device` is `None`. Returns the device that was plugged.
+    ///
+    /// The controller ports hold standard controllers by default, whose
+    /// buttons are set through `mut_controllers`. The expansion port is
+    /// empty.
+    pub fn set_input_device(
+        &mut self,
+        port: Port,
+        device: Option<Box<dyn InputDevice>>,
+    ) -> Option<Box<dyn InputDevice>> {
+        self.system.set_input_device(port, device)
+    }
+
+    /// Properties of the inserted cartridge, read from its header.
+    pub fn cartridge_metadata(&self) -> CartridgeMetadata {
+        self.system.cartridge_metadata()
+    }
+
+    /// TV system emulated by the console, chosen from the cartridge's
+    /// header when the console is created.
+    pub fn region(&self) -> Region {
+        self.system.region()
+    }
+
+    /// Emulates another TV system, e.g. to run a game whose header
+    /// doesn't say which region it was made for.
+    pub fn set_region(&mut self, region: Region) {
+        self.system.set_region(region);
+    }
+
+    /// Frames drawn per second by the console, about 60 for NTSC
+    /// and 50 for PAL and Dendy.
+    pub fn frame_rate(&self) -> f64 {
+        self.system.region().frame_rate()
+    }
+
+    /// Rate, in Hz, of the audio samples generated by the console.
+    pub fn sample_rate(&self) -> u32 {
+        self.system.sample_rate()
+    }
+
+    /// Changes the rate, in Hz, of the audio samples generated by
+    /// the console. Samples not yet taken are discarded.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.system.set_sample_rate(sample_rate);
+    }
+
+    /// Takes the mixed audio samples generated since the last call.
+    ///
+    /// Samples range from -1.0 to 1.0. At most one second of audio is
+    /// kept between calls.
+    pub fn take_audio_samples(&mut self) -> Vec<f32> {
+        self.system.take_audio_samples()
+    }
+
+    /// Copy of the cartridge's battery-backed RAM, to be written
+    /// to a `.sav` file. Returns `None` if the cartridge has no battery.
+    pub fn battery_ram(&self) -> Option<Vec<u8>> {
+        self.system.battery_ram()
+    }
+
+    /// Restores the cartridge's battery-backed RAM from the contents
+    /// of a `.sav` file.
+    pub fn load_battery_ram(&mut self, data: &[u8]) -> Result<(), CartridgeError> {
+        self.system.load_battery_ram(data)
+    }
+
+    /// Saves the state of the whole console.
+    ///
+    /// The ROM is not included, the state can only be loaded
+    /// into a console running the same game.
+    pub fn save_state(&self) -> Vec<u8> {
+        self.system.save_state()
+    }
+
+    /// Loads a state made by `save_state`.
+    ///
+    /// States made with other ROMs or other versions of the format are
+    /// rejected, leaving the console untouched.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        self.system.load_state(data)
+    }
+
+    /// Returns true if the CPU was halted by one of the JAM opcodes.
+    /// The console keeps drawing frames, but the game is frozen
+    /// until `system_reset` is called.
+    pub fn cpu_jammed(&self) -> bool {
+        self.system.cpu_jammed()
+    }
+
+    /// CPU cycles run since the console was powered on.
+    pub fn cpu_cycles(&self) -> u64 {
+        self.system.cpu_cycles()
+    }
+
+    /// Calls `hook` right before each instruction is executed.
+    /// Replaces the previous hook.
+    ///
+    /// The hook is not copied by `clone` and `fork`.
+    pub fn set_trace_hook(&mut self, hook: impl FnMut(&CpuTrace) + 'static) {
+        self.system.set_trace_hook(Some(Box::new(hook)));
+    }
+
+    pub fn clear_trace_hook(&mut self) {
+        self.system.set_trace_hook(None);
+    }
+
+    /// Starts logging every executed instruction with `tracer`.
+    /// Returns the previous tracer, if any.
+    ///
+    /// The tracer is not copied by `clone` and `fork`.
+    pub fn set_tracer(&mut self, tracer: Tracer) -> Option<Tracer> {
+        self.system.set_tracer(Some(tracer))
+    }
+
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.system.tracer()
+    }
+
+    /// Stops logging instructions, returning the tracer.
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.system.set_tracer(None)
+    }
+
+    /// Starts writing every completed frame, and the audio generated
+    /// with it, to a video. Returns the previous recorder, if any, which
+    /// should be completed with `Recorder::finish`.
+    ///
+    /// The recorder is not copied by `clone` and `fork`.
+    pub fn set_recorder(&mut self, recorder: Recorder) -> Option<Recorder> {
+        self.system.set_recorder(Some(recorder))
+    }
+
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.system.recorder()
+    }
+
+    /// Stops recording, returning the recorder to be completed with
+    /// `Recorder::finish`.
+    pub fn take_recorder(&mut self) -> Option<Recorder> {
+        self.system.set_recorder(None)
+    }
+
+    /// Disassembles the instructions found in `range` of the CPU's
+    /// address space.
+    ///
+    /// Memory is read without side effects, so this can be used while
+    /// the game runs. The last instruction may extend past the range.
+    pub fn disassemble(&self, range: RangeInclusive<u16>) -> Vec<disasm::Instruction> {
+        self.system.disassemble(range)
+    }
+
+    /// Reads a byte of the CPU's address space without side effects:
+    /// PPU and APU registers are not acknowledged and the controllers
+    /// are not shifted. Registers that can't be read return 0.
+    pub fn peek(&self, addr: u16) -> u8 {
+        self.system.peek(addr)
+    }
+
+    /// Reads a byte of the PPU's address space: pattern tables,
+    /// nametables and palettes.
+    pub fn peek_ppu(&self, addr: u16) -> u8 {
+        self.system.peek_ppu(addr)
+    }
+
+    /// Reads a byte of the sprite memory (OAM).
+    pub fn peek_oam(&self, index: u8) -> u8 {
+        self.system.peek_oam(index)
+    }
+
+    /// Writes a byte of the CPU's RAM or of the cartridge's RAM,
+    /// without triggering registers.
+    ///
+    /// Returns false if `addr` isn't mapped to RAM, e.g. if it's
+    /// mapped to a register or to the ROM.
+    pub fn poke(&mut self, addr: u16, data: u8) -> bool {
+        self.system.poke(addr, data)
+    }
+
+    /// Writes a byte of the PPU's address space. Writes to CHR-ROM
+    /// are ignored.
+    pub fn poke_ppu(&mut self, addr: u16, data: u8) {
+        self.system.poke_ppu(addr, data);
+    }
+
+    /// Writes a byte of the sprite memory (OAM).
+    pub fn poke_oam(&mut self, index: u8, data: u8) {
+        self.system.poke_oam(index, data);
+    }
+
+    /// Draws one of the two pattern tables, using one of the 8 palettes:
+    /// 0 to 3 for the background, 4 to 7 for the sprites.
+    pub fn draw_pattern_table(&self, table: u8, palette: u8) -> PatternTableScreen {
+        self.system.draw_pattern_table(table, palette)
+    }
+
+    /// Draws the four nametables, with the part shown at the start of
+    /// the frame outlined.
+    pub fn draw_nametables(&self) -> NametableScreen {
+        self.system.draw_nametables()
+    }
+
+    /// Draws the 64 sprites of the OAM.
+    pub fn draw_sprites(&self) -> SpriteScreen {
+        self.system.draw_sprites()
+    }
+
+    /// Draws the 32 palette entries.
+    pub fn draw_palettes(&self) -> PaletteScreen {
+        self.system.draw_palettes()
+    }
+
     pub fn system_clock(&mut self) {
         self.system.clock();
     }
@@ -44,6 +299,12 @@ impl Nes {
     pub fn system_reset(&mut self) {
         self.system.reset();
     }
+
+    /// Turns the console off and on, unlike `system_reset` which presses
+    /// the reset button. Only the cartridge's RAM is kept.
+    pub fn power_cycle(&mut self) {
+        self.system.power_cycle();
+    }
 }
 
 impl std::fmt::Display for Nes {
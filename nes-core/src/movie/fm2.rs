@@ -0,0 +1,272 @@
+//! FCEUX's FM2 movie format.
+//!
+//! An FM2 movie is a text file starting with a header of `key value`
+//! lines, followed by one line per frame:
+//!
+//! ```text
+//! version 3
+//! rerecordCount 12
+//! romFilename game
+//! port0 1
+//! port1 1
+//! port2 0
+//! |0|....T...|........||
+//! |1|R......A|........||
+//! ```
+//!
+//! Each frame line holds the commands to run (1 for reset, 2 for power)
+//! and the buttons of each controller, in the order `RLDUTSBA`, where
+//! `T` is start and `S` select. Any character other than `.` or a space
+//! means the button is held.
+//!
+//! FCEUX requires the `romChecksum` and `guid` keys, which are written
+//! for every movie recorded by this emulator.
+//!
+//! Movies starting from a save state store it under the `savestate`
+//! key. Those states are made by this emulator, so they can't be shared
+//! with FCEUX.
+
+use std::collections::hash_map::RandomState;
+use std::fmt::Write;
+use std::hash::{BuildHasher, Hasher};
+
+use super::{Movie, MovieCommands, MovieError, MovieFrame, MovieStart};
+use crate::controller::Controller;
+use crate::util;
+
+/// Buttons in the order of the frame lines.
+const BUTTONS: [(char, Controller); 8] = [
+    ('R', Controller::RIGHT),
+    ('L', Controller::LEFT),
+    ('D', Controller::DOWN),
+    ('U', Controller::UP),
+    ('T', Controller::START),
+    ('S', Controller::SELECT),
+    ('B', Controller::BUTTON_B),
+    ('A', Controller::BUTTON_A),
+];
+
+/// Value of the `port` keys for a standard controller.
+const PORT_GAMEPAD: &str = "1";
+/// Value of the `port` keys for an empty port.
+const PORT_NONE: &str = "0";
+
+impl Movie {
+    /// Reads a movie in the FM2 format.
+    ///
+    /// Text movies using standard controllers on the first two ports
+    /// are supported. Unknown keys are ignored.
+    pub fn from_fm2(text: &str) -> Result<Self, MovieError> {
+        let mut movie = Movie::default();
+        let mut ports = [true; 2];
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            let error = |message: &str| MovieError::FormatError(i + 1, message.into());
+
+            if line.starts_with('|') {
+                movie.frames.push(parse_frame(line, ports).map_err(error)?);
+                continue;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "version" if value != "3" => {
+                    return Err(MovieError::UnsupportedError(format!("version {value}")))
+                }
+                "binary" if value != "0" => {
+                    return Err(MovieError::UnsupportedError("binary input".into()))
+                }
+                "fourscore" if value != "0" => {
+                    return Err(MovieError::UnsupportedError("Four Score".into()))
+                }
+                "port0" | "port1" => {
+                    let port = usize::from(key == "port1");
+                    ports[port] = match value {
+                        PORT_GAMEPAD => true,
+                        PORT_NONE => false,
+                        _ => {
+                            return Err(MovieError::UnsupportedError(format!(
+                                "device {value} on {key}"
+                            )))
+                        }
+                    };
+                }
+                "port2" if value != PORT_NONE => {
+                    return Err(MovieError::UnsupportedError(
+                        "expansion port devices".into(),
+                    ))
+                }
+                "rerecordCount" => {
+                    movie.rerecord_count = value.parse().map_err(|_| error("invalid count"))?
+                }
+                "palFlag" => movie.pal = value == "1",
+                "romFilename" => movie.rom_filename = value.into(),
+                "romChecksum" => movie.rom_checksum = Some(value.into()),
+                "guid" => movie.guid = value.into(),
+                "comment" => movie.comments.push(value.into()),
+                "savestate" => {
+                    let state = parse_bytes(value).ok_or_else(|| error("invalid save state"))?;
+                    movie.start = MovieStart::SaveState(state);
+                }
+                _ => {}
+            }
+        }
+
+        if movie.guid.is_empty() {
+            movie.guid = new_guid();
+        }
+        Ok(movie)
+    }
+
+    /// Writes the movie in the FM2 format.
+    pub fn to_fm2(&self) -> String {
+        let mut text = String::new();
+
+        // `writeln!` on a `String` never fails
+        writeln!(text, "version 3").unwrap();
+        // version of FCEUX whose format is followed
+        writeln!(text, "emuVersion 22020").unwrap();
+        writeln!(text, "rerecordCount {}", self.rerecord_count).unwrap();
+        writeln!(text, "palFlag {}", u8::from(self.pal)).unwrap();
+        writeln!(text, "romFilename {}", self.rom_filename).unwrap();
+        if let Some(checksum) = &self.rom_checksum {
+            writeln!(text, "romChecksum {checksum}").unwrap();
+        }
+        writeln!(text, "guid {}", self.guid).unwrap();
+        writeln!(text, "fourscore 0").unwrap();
+        writeln!(text, "port0 {PORT_GAMEPAD}").unwrap();
+        writeln!(text, "port1 {PORT_GAMEPAD}").unwrap();
+        writeln!(text, "port2 {PORT_NONE}").unwrap();
+        for comment in &self.comments {
+            writeln!(text, "comment {comment}").unwrap();
+        }
+        if let MovieStart::SaveState(state) = &self.start {
+            writeln!(text, "savestate base64:{}", util::base64_encode(state)).unwrap();
+        }
+
+        for frame in &self.frames {
+            let [controller1, controller2] = frame.controllers;
+            writeln!(
+                text,
+                "|{}|{}|{}||",
+                frame.commands.bits(),
+                buttons_text(controller1),
+                buttons_text(controller2)
+            )
+            .unwrap();
+        }
+
+        text
+    }
+}
+
+/// Generates an identifier for a new movie, formatted like FCEUX's,
+/// e.g. `452DE2C3-EF43-4FA9-A9C1-F4B3F7A2C2C8`.
+pub(super) fn new_guid() -> String {
+    // each `RandomState` is seeded differently
+    let random = || RandomState::new().build_hasher().finish();
+    let (high, low) = (random(), random());
+    format!(
+        "{:08X}-{:04X}-{:04X}-{:04X}-{:012X}",
+        high >> 32,
+        (high >> 16) & 0xFFFF,
+        high & 0xFFFF,
+        low >> 48,
+        low & 0xFFFF_FFFF_FFFF
+    )
+}
+
+/// Parses `|commands|port0|port1|port2|`. Ports without a controller
+/// have an empty field.
+fn parse_frame(line: &str, ports: [bool; 2]) -> Result<MovieFrame, &'static str> {
+    let mut fields = line.split('|').skip(1);
+
+    let commands = fields
+        .next()
+        .and_then(|commands| commands.trim().parse().ok())
+        .ok_or("invalid commands")?;
+
+    let mut frame =
+        MovieFrame::default().with_commands(MovieCommands::from_bits_truncate(commands));
+    for (controller, connected) in frame.controllers.iter_mut().zip(ports) {
+        let field = fields.next().ok_or("missing controller")?;
+        if connected {
+            *controller = parse_buttons(field).ok_or("invalid controller")?;
+        }
+    }
+
+    Ok(frame)
+}
+
+fn parse_buttons(field: &str) -> Option<Controller> {
+    if field.chars().count() != BUTTONS.len() {
+        return None;
+    }
+
+    let controller = field
+        .chars()
+        .zip(BUTTONS)
+        .filter(|&(c, _)| c != '.' && c != ' ')
+        .fold(Controller::empty(), |controller, (_, (_, button))| {
+            controller | button
+        });
+    Some(controller)
+}
+
+fn buttons_text(controller: Controller) -> String {
+    BUTTONS
+        .iter()
+        .map(|&(c, button)| if controller.contains(button) { c } else { '.' })
+        .collect()
+}
+
+/// Parses binary data stored as `base64:...` or as `0x` followed by
+/// hexadecimal digits.
+fn parse_bytes(value: &str) -> Option<Vec<u8>> {
+    if let Some(base64) = value.strip_prefix("base64:") {
+        return util::base64_decode(base64);
+    }
+
+    let hex = value.strip_prefix("0x")?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[test]
+fn test_fm2() {
+    let text = "version 3\nrerecordCount 7\nromFilename smb\nport0 1\nport1 0\nport2 0\n\
+                comment author someone\n|0|....T...||||\n|1|R......A||||\n";
+    let movie = Movie::from_fm2(text).unwrap();
+
+    assert_eq!(movie.rerecord_count, 7);
+    assert_eq!(movie.rom_filename, "smb");
+    assert_eq!(movie.comments, ["author someone"]);
+    assert_eq!(movie.frames()[0].controllers[0], Controller::START);
+    assert_eq!(movie.frames()[1].commands, MovieCommands::RESET);
+    assert_eq!(
+        movie.frames()[1].controllers[0],
+        Controller::RIGHT | Controller::BUTTON_A
+    );
+
+    let exported = movie.to_fm2();
+    assert!(exported.contains("|1|R......A|........||\n"));
+    assert_eq!(Movie::from_fm2(&exported).unwrap(), movie);
+
+    assert!(matches!(
+        Movie::from_fm2("version 3\n|0|RL|........||\n"),
+        Err(MovieError::FormatError(2, _))
+    ));
+    assert!(matches!(
+        Movie::from_fm2("version 3\nport0 2\n"),
+        Err(MovieError::UnsupportedError(_))
+    ));
+}
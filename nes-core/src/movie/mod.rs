@@ -0,0 +1,250 @@
+//! Input movies.
+//!
+//! A movie holds the buttons held on both controllers during each frame,
+//! from power-on or from a save state. Playing the movie back presses
+//! the same buttons on the same frames, and since the console is
+//! deterministic, the game runs exactly as it did while recording.
+//!
+//! Movies are imported from and exported to FCEUX's FM2 format, see
+//! `Movie::from_fm2`.
+
+mod fm2;
+
+use bitflags::bitflags;
+use thiserror::Error;
+
+use crate::controller::Controller;
+use crate::region::Region;
+use crate::state::StateError;
+use crate::util;
+use crate::Nes;
+
+bitflags! {
+    /// Commands run at the start of a frame, before the input is read.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+    pub struct MovieCommands: u8 {
+        /// Presses the reset button
+        const RESET = 0b0000_0001;
+        /// Turns the console off and on
+        const POWER = 0b0000_0010;
+    }
+}
+
+/// Input of one frame of a movie.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MovieFrame {
+    pub commands: MovieCommands,
+    pub controllers: [Controller; 2],
+}
+
+impl MovieFrame {
+    pub fn new(controllers: [Controller; 2]) -> Self {
+        MovieFrame {
+            commands: MovieCommands::empty(),
+            controllers,
+        }
+    }
+
+    pub fn with_commands(mut self, commands: MovieCommands) -> Self {
+        self.commands = commands;
+        self
+    }
+
+    /// Runs the commands and sets the controllers of the console, which
+    /// is then ready to run the frame.
+    pub fn apply(&self, nes: &mut Nes) {
+        if self.commands.contains(MovieCommands::POWER) {
+            nes.power_cycle();
+        } else if self.commands.contains(MovieCommands::RESET) {
+            nes.system_reset();
+        }
+        *nes.mut_controllers() = self.controllers;
+    }
+}
+
+impl Default for MovieFrame {
+    fn default() -> Self {
+        Self::new([Controller::empty(); 2])
+    }
+}
+
+/// State of the console when a movie starts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum MovieStart {
+    /// The console is turned on
+    #[default]
+    PowerOn,
+    /// A save state made by `Nes::save_state` is loaded
+    SaveState(Vec<u8>),
+}
+
+/// Movie Error
+///
+/// - FormatError: The FM2 text is invalid, on the given line
+/// - UnsupportedError: The FM2 movie uses a feature that isn't emulated
+/// - StateError: The save state the movie starts from can't be loaded
+#[derive(Error, Debug)]
+pub enum MovieError {
+    #[error("Invalid FM2 movie, line {0}: {1}")]
+    FormatError(usize, String),
+    #[error("Unsupported FM2 movie: {0}")]
+    UnsupportedError(String),
+    #[error("Couldn't load the movie's save state: {0}")]
+    StateError(#[from] StateError),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Movie {
+    pub start: MovieStart,
+    frames: Vec<MovieFrame>,
+    /// Times the recording went back to an earlier frame
+    pub rerecord_count: u32,
+    /// Whether the movie was recorded on a PAL console
+    pub pal: bool,
+
+    /// Name of the ROM, as stored in FM2 movies
+    pub rom_filename: String,
+    /// Checksum of the ROM, as stored in FM2 movies: `base64:` followed
+    /// by the MD5 of the ROM. It is not checked against the running ROM.
+    pub rom_checksum: Option<String>,
+    /// Identifies the movie, e.g. to match it with its save states
+    pub guid: String,
+    /// Lines of free text, e.g. the author
+    pub comments: Vec<String>,
+}
+
+impl Movie {
+    /// Creates an empty movie of the game run by `nes`, starting from
+    /// power-on.
+    pub fn new(nes: &Nes) -> Self {
+        Movie {
+            pal: nes.region() == Region::Pal,
+            rom_checksum: Some(format!("base64:{}", util::base64_encode(&nes.rom_md5()))),
+            guid: fm2::new_guid(),
+            ..Self::default()
+        }
+    }
+
+    /// Creates an empty movie starting from the current state of `nes`.
+    pub fn from_state(nes: &Nes) -> Self {
+        Movie {
+            start: MovieStart::SaveState(nes.save_state()),
+            ..Self::new(nes)
+        }
+    }
+
+    pub fn frames(&self) -> &[MovieFrame] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Puts the console in the state the movie starts from: turns it
+    /// off and on, or loads the movie's save state.
+    ///
+    /// PAL movies switch the console to PAL, other movies switch PAL
+    /// consoles to NTSC.
+    pub fn restart(&self, nes: &mut Nes) -> Result<(), MovieError> {
+        if self.pal {
+            nes.set_region(Region::Pal);
+        } else if nes.region() == Region::Pal {
+            nes.set_region(Region::Ntsc);
+        }
+
+        match &self.start {
+            MovieStart::PowerOn => nes.power_cycle(),
+            MovieStart::SaveState(state) => nes.load_state(state)?,
+        }
+        Ok(())
+    }
+
+    /// Applies `frame` to the console and adds it to the movie. To be
+    /// called before each frame is run while recording.
+    pub fn record(&mut self, frame: MovieFrame, nes: &mut Nes) {
+        frame.apply(nes);
+        self.frames.push(frame);
+    }
+
+    /// Applies the input of frame `index` to the console, to be called
+    /// before each frame is run while playing the movie back. Returns
+    /// false once the movie is over.
+    pub fn play(&self, index: usize, nes: &mut Nes) -> bool {
+        match self.frames.get(index) {
+            Some(frame) => {
+                frame.apply(nes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the frames from `index` on, to record them again, e.g.
+    /// after loading an earlier save state. Counts as a rerecord.
+    pub fn rerecord_from(&mut self, index: usize) {
+        self.frames.truncate(index);
+        self.rerecord_count += 1;
+    }
+}
+
+#[test]
+fn test_movie() {
+    use crate::cartridge::Cartridge;
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    let mut movie = Movie::new(&nes);
+    movie.restart(&mut nes).unwrap();
+
+    for i in 0..120u32 {
+        let buttons = match i % 40 {
+            0..=9 => Controller::START,
+            20..=29 => Controller::DOWN | Controller::BUTTON_A,
+            _ => Controller::empty(),
+        };
+        let mut frame = MovieFrame::new([buttons, Controller::empty()]);
+        if i == 60 {
+            frame = frame.with_commands(MovieCommands::RESET);
+        }
+        movie.record(frame, &mut nes);
+        nes.next_frame();
+    }
+    let hash = nes.screen().crc32();
+    let ram: Vec<u8> = (0..0x800).map(|addr| nes.peek(addr)).collect();
+
+    // played back from an FM2 export, on a console that ran other frames
+    let exported = movie.to_fm2();
+    assert!(exported.contains("romChecksum base64:9oQylYzYDnjzZPhydnmhcA==\n"));
+    assert!(exported.contains(&format!("guid {}\n", movie.guid)));
+    let movie = Movie::from_fm2(&exported).unwrap();
+    assert_eq!(movie.len(), 120);
+    nes.next_frame();
+    movie.restart(&mut nes).unwrap();
+
+    let mut index = 0;
+    while movie.play(index, &mut nes) {
+        nes.next_frame();
+        index += 1;
+    }
+    assert_eq!(nes.screen().crc32(), hash);
+    assert!((0..0x800).all(|addr| nes.peek(addr) == ram[addr as usize]));
+}
+
+#[test]
+fn test_movie_region() {
+    use crate::cartridge::Cartridge;
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    let mut movie = Movie::new(&nes);
+    movie.pal = true;
+
+    movie.restart(&mut nes).unwrap();
+    assert_eq!(nes.region(), Region::Pal);
+    movie.pal = false;
+    movie.restart(&mut nes).unwrap();
+    assert_eq!(nes.region(), Region::Ntsc);
+}
@@ -0,0 +1,270 @@
+//! Uncompressed AVI videos.
+//!
+//! The video is made of a header listing one video stream and one audio
+//! stream, a `movi` list holding a chunk of each stream per frame, and
+//! an index of those chunks. The header holds the amount of frames and
+//! samples, so it is completed once the recording ends.
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::screen::{NesScreen, NES_HEIGHT, NES_WIDTH};
+
+/// Bytes of a frame stored as a 24-bit bitmap.
+const FRAME_SIZE: u32 = (NES_WIDTH * NES_HEIGHT * 3) as u32;
+
+/// Flag of the main header: the video has an index.
+const AVIF_HASINDEX: u32 = 0x10;
+/// Flag of the main header: the chunks of the streams are interleaved.
+const AVIF_ISINTERLEAVED: u32 = 0x100;
+/// Flag of the index entries: every frame is a key frame.
+const AVIIF_KEYFRAME: u32 = 0x10;
+
+/// Positions, relative to the start of the video, of the fields
+/// completed by `finish`.
+#[derive(Clone, Copy, Debug, Default)]
+struct Fields {
+    riff_size: usize,
+    total_frames: usize,
+    video_length: usize,
+    audio_length: usize,
+    movi_size: usize,
+}
+
+pub(super) struct AviWriter<W> {
+    writer: W,
+    /// Position of the video in the writer
+    start: u64,
+    fields: Fields,
+    /// Id and size of the chunks of the `movi` list, for the index
+    chunks: Vec<([u8; 4], u32)>,
+    /// Bytes written to the `movi` list, after its type
+    movi_size: u64,
+    frames: u32,
+    samples: u32,
+}
+
+impl<W: Write + Seek> AviWriter<W> {
+    pub fn new(writer: W) -> Self {
+        AviWriter {
+            writer,
+            start: 0,
+            fields: Fields::default(),
+            chunks: Vec::new(),
+            movi_size: 0,
+            frames: 0,
+            samples: 0,
+        }
+    }
+
+    /// Writes the headers, with a frame rate of `rate / scale` frames
+    /// per second, and starts the `movi` list.
+    pub fn write_header(&mut self, (rate, scale): (u32, u32), sample_rate: u32) -> io::Result<()> {
+        let (width, height) = (NES_WIDTH as u32, NES_HEIGHT as u32);
+        let frame_time = (1_000_000 * scale as u64 / rate as u64) as u32;
+        let bytes_per_second =
+            (FRAME_SIZE as u64 * rate as u64 / scale as u64) as u32 + sample_rate * 2;
+
+        let mut header = Header::default();
+        header.fourcc(b"RIFF");
+        self.fields.riff_size = header.u32(0);
+        header.fourcc(b"AVI ");
+
+        let hdrl = header.start_list(b"hdrl");
+        header.fourcc(b"avih");
+        header.u32(56);
+        header.u32(frame_time);
+        header.u32(bytes_per_second);
+        header.u32(0); // padding granularity
+        header.u32(AVIF_HASINDEX | AVIF_ISINTERLEAVED);
+        self.fields.total_frames = header.u32(0);
+        header.u32(0); // initial frames
+        header.u32(2); // streams
+        header.u32(FRAME_SIZE); // suggested buffer size
+        header.u32(width);
+        header.u32(height);
+        header.bytes(&[0; 16]); // reserved
+
+        let strl = header.start_list(b"strl");
+        header.fourcc(b"strh");
+        header.u32(56);
+        header.fourcc(b"vids");
+        header.fourcc(b"DIB ");
+        header.u32(0); // flags
+        header.u32(0); // priority and language
+        header.u32(0); // initial frames
+        header.u32(scale);
+        header.u32(rate);
+        header.u32(0); // start
+        self.fields.video_length = header.u32(0);
+        header.u32(FRAME_SIZE); // suggested buffer size
+        header.u32(u32::MAX); // default quality
+        header.u32(0); // sample size, varies for video
+        header.bytes(&[0; 4]); // frame rectangle: left and top
+        header.bytes(&(width as u16).to_le_bytes());
+        header.bytes(&(height as u16).to_le_bytes());
+
+        // BITMAPINFOHEADER
+        header.fourcc(b"strf");
+        header.u32(40);
+        header.u32(40);
+        header.u32(width);
+        header.u32(height); // positive, rows go from bottom to top
+        header.bytes(&1u16.to_le_bytes()); // planes
+        header.bytes(&24u16.to_le_bytes()); // bits per pixel
+        header.u32(0); // uncompressed RGB
+        header.u32(FRAME_SIZE);
+        header.bytes(&[0; 16]); // resolution and palette
+        header.end_list(strl);
+
+        let strl = header.start_list(b"strl");
+        header.fourcc(b"strh");
+        header.u32(56);
+        header.fourcc(b"auds");
+        header.u32(0); // handler
+        header.u32(0); // flags
+        header.u32(0); // priority and language
+        header.u32(0); // initial frames
+        header.u32(1); // scale
+        header.u32(sample_rate);
+        header.u32(0); // start
+        self.fields.audio_length = header.u32(0);
+        header.u32(sample_rate * 2); // suggested buffer size
+        header.u32(u32::MAX); // default quality
+        header.u32(2); // sample size
+        header.bytes(&[0; 8]); // frame rectangle
+
+        // PCMWAVEFORMAT, mono
+        header.fourcc(b"strf");
+        header.u32(16);
+        header.bytes(&1u16.to_le_bytes()); // PCM
+        header.bytes(&1u16.to_le_bytes()); // channels
+        header.u32(sample_rate);
+        header.u32(sample_rate * 2); // bytes per second
+        header.bytes(&2u16.to_le_bytes()); // block align
+        header.bytes(&16u16.to_le_bytes()); // bits per sample
+        header.end_list(strl);
+        header.end_list(hdrl);
+
+        header.fourcc(b"LIST");
+        self.fields.movi_size = header.u32(0);
+        header.fourcc(b"movi");
+
+        self.start = self.writer.stream_position()?;
+        self.writer.write_all(&header.0)
+    }
+
+    /// Writes a chunk with the frame, and one with the audio samples.
+    pub fn write_frame(&mut self, screen: &NesScreen, samples: &[f32]) -> io::Result<()> {
+        let audio_size = samples.len() as u64 * 2;
+        // the chunks, the index entries and the `idx1` header must fit
+        let size = self.file_size() + 8 + FRAME_SIZE as u64 + 8 + audio_size + 32 + 8;
+        if size > u32::MAX as u64 {
+            return Err(io::Error::other("AVI videos are limited to 4 GB"));
+        }
+
+        // bitmaps are stored from the bottom row up, in BGR order
+        let mut frame = Vec::with_capacity(FRAME_SIZE as usize);
+        for row in screen.draw_buffer().iter().rev() {
+            frame.extend(row.iter().flat_map(|pixel| [pixel.b, pixel.g, pixel.r]));
+        }
+        self.write_chunk(*b"00db", &frame)?;
+        self.frames += 1;
+
+        if !samples.is_empty() {
+            let audio: Vec<u8> = samples
+                .iter()
+                .flat_map(|&sample| {
+                    ((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes()
+                })
+                .collect();
+            self.write_chunk(*b"01wb", &audio)?;
+            self.samples += samples.len() as u32;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the index, completes the headers and flushes the writer.
+    pub fn finish(&mut self) -> io::Result<()> {
+        let mut index = Header::default();
+        index.fourcc(b"idx1");
+        index.u32(self.chunks.len() as u32 * 16);
+        // offsets start at the type of the `movi` list
+        let mut offset = 4;
+        for (id, size) in &self.chunks {
+            index.fourcc(id);
+            index.u32(AVIIF_KEYFRAME);
+            index.u32(offset);
+            index.u32(*size);
+            offset += 8 + size;
+        }
+        self.writer.write_all(&index.0)?;
+
+        let end = self.writer.stream_position()?;
+        let fields = self.fields;
+        self.patch(fields.riff_size, (end - self.start - 8) as u32)?;
+        self.patch(fields.total_frames, self.frames)?;
+        self.patch(fields.video_length, self.frames)?;
+        self.patch(fields.audio_length, self.samples)?;
+        self.patch(fields.movi_size, 4 + self.movi_size as u32)?;
+
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    fn write_chunk(&mut self, id: [u8; 4], data: &[u8]) -> io::Result<()> {
+        // chunks are padded to an even size, which both kinds of chunk already have
+        self.writer.write_all(&id)?;
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.writer.write_all(data)?;
+
+        self.chunks.push((id, data.len() as u32));
+        self.movi_size += 8 + data.len() as u64;
+        Ok(())
+    }
+
+    /// Size of the video, with the index that isn't written yet.
+    fn file_size(&self) -> u64 {
+        self.fields.movi_size as u64 + 8 + self.movi_size + 8 + self.chunks.len() as u64 * 16
+    }
+
+    fn patch(&mut self, position: usize, value: u32) -> io::Result<()> {
+        self.writer
+            .seek(SeekFrom::Start(self.start + position as u64))?;
+        self.writer.write_all(&value.to_le_bytes())
+    }
+}
+
+/// Bytes of the headers, little-endian.
+#[derive(Default)]
+struct Header(Vec<u8>);
+
+impl Header {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn fourcc(&mut self, fourcc: &[u8; 4]) {
+        self.bytes(fourcc);
+    }
+
+    /// Returns the position of the value, to be completed later.
+    fn u32(&mut self, value: u32) -> usize {
+        let position = self.0.len();
+        self.bytes(&value.to_le_bytes());
+        position
+    }
+
+    /// Starts a list, returning the position of its size.
+    fn start_list(&mut self, kind: &[u8; 4]) -> usize {
+        self.fourcc(b"LIST");
+        let position = self.u32(0);
+        self.fourcc(kind);
+        position
+    }
+
+    fn end_list(&mut self, position: usize) {
+        let size = (self.0.len() - position - 4) as u32;
+        self.0[position..position + 4].copy_from_slice(&size.to_le_bytes());
+    }
+}
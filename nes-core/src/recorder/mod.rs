@@ -0,0 +1,234 @@
+//! Video recordings of the game.
+//!
+//! A `Recorder` installed with `Nes::set_recorder` receives every frame
+//! completed by the PPU, along with the audio generated during that
+//! frame, and writes them to a Y4M or an AVI video at the exact frame
+//! rate of the console. Frames are not compressed, so videos take about
+//! 11 MB per second.
+
+mod avi;
+
+use std::fmt;
+use std::io::{self, Seek, Write};
+
+use crate::region::Region;
+use crate::screen::{NesScreen, NES_HEIGHT, NES_WIDTH};
+use avi::AviWriter;
+
+/// Writers that can go back to fill the headers of AVI videos.
+trait WriteSeek: Write + Seek {}
+
+impl<T: Write + Seek> WriteSeek for T {}
+
+enum RecordOutput {
+    /// Video only, the audio is dropped
+    Y4m(Box<dyn Write>),
+    Avi(AviWriter<Box<dyn WriteSeek>>),
+}
+
+/// Writes the frames and the audio of the console to a video.
+pub struct Recorder {
+    output: RecordOutput,
+    /// Set once the header is written
+    started: bool,
+    frames: u32,
+    /// First error returned by the writer, after which nothing is written
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    /// Creates a recorder that writes a YUV4MPEG2 (Y4M) video to
+    /// `writer`, without audio.
+    ///
+    /// Frames are converted to YUV with the BT.601 coefficients, without
+    /// chroma subsampling. The writer is not buffered by the recorder.
+    /// Wrap files in a `BufWriter`.
+    pub fn y4m(writer: impl Write + 'static) -> Self {
+        Self::with_output(RecordOutput::Y4m(Box::new(writer)))
+    }
+
+    /// Creates a recorder that writes an AVI video to `writer`, with
+    /// the frames as RGB bitmaps and the audio as 16-bit PCM.
+    ///
+    /// The headers are only complete once `finish` is called. AVI
+    /// videos are limited to 4 GB, about 6 minutes of gameplay: the
+    /// recorder stops with an error once the limit is reached.
+    pub fn avi(writer: impl Write + Seek + 'static) -> Self {
+        let writer: Box<dyn WriteSeek> = Box::new(writer);
+        Self::with_output(RecordOutput::Avi(AviWriter::new(writer)))
+    }
+
+    fn with_output(output: RecordOutput) -> Self {
+        Recorder {
+            output,
+            started: false,
+            frames: 0,
+            error: None,
+        }
+    }
+
+    /// Frames recorded so far.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// The error returned by the writer, if any. The recorder stops
+    /// writing after the first error.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Completes the video and flushes the writer.
+    ///
+    /// Returns the first error met while recording, if any.
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        match &mut self.output {
+            RecordOutput::Y4m(writer) => writer.flush(),
+            RecordOutput::Avi(writer) if self.started => writer.finish(),
+            RecordOutput::Avi(_) => Ok(()),
+        }
+    }
+
+    /// Writes the header of the video. Does nothing if the recorder was
+    /// already started.
+    pub(crate) fn start(&mut self, region: Region, sample_rate: u32) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+
+        let (rate, scale) = region.frame_rate_fraction();
+        let result = match &mut self.output {
+            RecordOutput::Y4m(writer) => writeln!(
+                writer,
+                "YUV4MPEG2 W{NES_WIDTH} H{NES_HEIGHT} F{rate}:{scale} Ip A1:1 C444"
+            ),
+            RecordOutput::Avi(writer) => writer.write_header((rate, scale), sample_rate),
+        };
+        self.check(result);
+    }
+
+    /// Writes a frame, and the audio samples generated while it was drawn.
+    pub(crate) fn record_frame(&mut self, screen: &NesScreen, samples: &[f32]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let result = match &mut self.output {
+            RecordOutput::Y4m(writer) => write_y4m_frame(writer, screen),
+            RecordOutput::Avi(writer) => writer.write_frame(screen, samples),
+        };
+        self.check(result);
+        if self.error.is_none() {
+            self.frames += 1;
+        }
+    }
+
+    fn check(&mut self, result: io::Result<()>) {
+        if let Err(error) = result {
+            self.error.get_or_insert(error);
+        }
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let output = match &self.output {
+            RecordOutput::Y4m(_) => "Y4m",
+            RecordOutput::Avi(_) => "Avi",
+        };
+        f.debug_struct("Recorder")
+            .field("output", &output)
+            .field("started", &self.started)
+            .field("frames", &self.frames)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+/// Writes the Y, U and V planes of a frame, at full resolution.
+fn write_y4m_frame(writer: &mut impl Write, screen: &NesScreen) -> io::Result<()> {
+    const PLANE_SIZE: usize = NES_WIDTH * NES_HEIGHT;
+
+    let mut planes = vec![0; PLANE_SIZE * 3];
+    for (i, pixel) in screen.flatten().enumerate() {
+        let (r, g, b) = (pixel.r as i32, pixel.g as i32, pixel.b as i32);
+        // BT.601, with luma from 16 to 235 and chroma from 16 to 240
+        planes[i] = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+        planes[PLANE_SIZE + i] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
+        planes[PLANE_SIZE * 2 + i] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+    }
+
+    writer.write_all(b"FRAME\n")?;
+    writer.write_all(&planes)
+}
+
+#[test]
+fn test_recorder() {
+    use std::cell::RefCell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    use crate::cartridge::Cartridge;
+    use crate::Nes;
+
+    /// Keeps the written bytes readable after the recorder is finished.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Cursor<Vec<u8>>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for Shared {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            self.0.borrow_mut().seek(pos)
+        }
+    }
+
+    let u32_at = |bytes: &[u8], offset: usize| {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    };
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+
+    let y4m = Shared::default();
+    nes.set_recorder(Recorder::y4m(y4m.clone()));
+    nes.next_frame();
+    nes.next_frame();
+    let recorder = nes.take_recorder().unwrap();
+    assert_eq!(recorder.frames(), 2);
+    recorder.finish().unwrap();
+
+    let y4m = y4m.0.borrow().get_ref().clone();
+    let header = b"YUV4MPEG2 W256 H240 F39375000:655171 Ip A1:1 C444\n";
+    assert!(y4m.starts_with(header));
+    assert_eq!(
+        y4m.len(),
+        header.len() + 2 * (6 + NES_WIDTH * NES_HEIGHT * 3)
+    );
+
+    let avi = Shared::default();
+    nes.set_recorder(Recorder::avi(avi.clone()));
+    for _ in 0..3 {
+        nes.next_frame();
+    }
+    nes.take_recorder().unwrap().finish().unwrap();
+
+    let avi = avi.0.borrow().get_ref().clone();
+    assert_eq!(&avi[..4], b"RIFF");
+    assert_eq!(u32_at(&avi, 4) as usize, avi.len() - 8);
+    assert_eq!(&avi[8..12], b"AVI ");
+    // total frames, in the main header
+    assert_eq!(u32_at(&avi, 48), 3);
+    assert_eq!(&avi[avi.len() - 16 * 6 - 8..][..4], b"idx1");
+}
@@ -0,0 +1,116 @@
+//! TV system emulated by the console.
+//!
+//! NTSC, PAL and Dendy consoles run their CPU and PPU at different
+//! speeds and draw a different amount of scanlines per frame, so games
+//! made for one of them run too fast or too slow on the others.
+
+use crate::cartridge::CartridgeRegion;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    /// North America and Japan
+    #[default]
+    Ntsc,
+    /// Europe and Australia
+    Pal,
+    /// Famiclone sold in Russia, with PAL timings that stay compatible
+    /// with NTSC games
+    Dendy,
+}
+
+impl Region {
+    /// Region of the console a cartridge was made for. Games that work
+    /// on every console run as NTSC.
+    pub fn from_cartridge(region: CartridgeRegion) -> Self {
+        match region {
+            CartridgeRegion::Ntsc | CartridgeRegion::Multi => Region::Ntsc,
+            CartridgeRegion::Pal => Region::Pal,
+            CartridgeRegion::Dendy => Region::Dendy,
+        }
+    }
+
+    /// Frequency of the CPU, in Hz.
+    pub fn cpu_frequency(self) -> f64 {
+        match self {
+            Region::Ntsc => 1_789_773.0,
+            Region::Pal => 1_662_607.0,
+            Region::Dendy => 1_773_448.0,
+        }
+    }
+
+    /// Frames drawn per second.
+    pub fn frame_rate(self) -> f64 {
+        let ppu_frequency = self.cpu_frequency() * self.ppu_cycles_per_cpu_cycle();
+        // NTSC frames are half a dot shorter on average, because of the
+        // dot skipped on odd frames while rendering
+        let dots = match self {
+            Region::Ntsc => 341.0 * 262.0 - 0.5,
+            Region::Pal | Region::Dendy => 341.0 * 312.0,
+        };
+
+        ppu_frequency / dots
+    }
+
+    /// Exact frames per second, as a numerator and a denominator, e.g.
+    /// for the headers of video files.
+    pub fn frame_rate_fraction(self) -> (u32, u32) {
+        match self {
+            // PPU clocked at 236.25 MHz / 11 / 4, 341 * 262 - 0.5 dots per frame
+            Region::Ntsc => (39_375_000, 655_171),
+            // PPU clocked at 26.6017125 MHz / 5, 341 * 312 dots per frame
+            Region::Pal | Region::Dendy => (322_445, 6_448),
+        }
+    }
+
+    /// Ratio between the speed of the PPU and of the CPU: 3 for NTSC
+    /// and Dendy, 3.2 for PAL.
+    pub fn ppu_cycles_per_cpu_cycle(self) -> f64 {
+        let (ppu, cpu) = self.clock_ratio();
+        ppu as f64 / cpu as f64
+    }
+
+    /// PPU cycles and CPU cycles that take the same time.
+    pub(crate) fn clock_ratio(self) -> (u64, u64) {
+        match self {
+            Region::Ntsc | Region::Dendy => (3, 1),
+            Region::Pal => (16, 5),
+        }
+    }
+
+    /// Last scanline of a frame, before the pre-render scanline.
+    pub(crate) fn last_scanline(self) -> i16 {
+        match self {
+            Region::Ntsc => 260,
+            Region::Pal | Region::Dendy => 310,
+        }
+    }
+
+    /// Scanline on which the vertical blank starts.
+    pub(crate) fn vblank_scanline(self) -> i16 {
+        match self {
+            Region::Ntsc | Region::Pal => 241,
+            // Dendy keeps the vertical blank of NTSC consoles, and adds
+            // its extra scanlines before it
+            Region::Dendy => 291,
+        }
+    }
+}
+
+#[test]
+fn test_pal_timings() {
+    use crate::cartridge::Cartridge;
+    use crate::Nes;
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    assert_eq!(nes.region(), Region::Ntsc);
+    nes.set_region(Region::Pal);
+    assert!((nes.frame_rate() - 50.007).abs() < 0.001);
+
+    nes.next_frame();
+    let start = nes.cpu_cycles();
+    nes.next_frame();
+
+    // 312 scanlines of 341 dots, with 5 CPU cycles every 16 dots,
+    // which makes 33247.5 CPU cycles per frame
+    assert!((33247..=33248).contains(&(nes.cpu_cycles() - start)));
+}
@@ -0,0 +1,251 @@
+//! Rewind buffer.
+//!
+//! Keeps periodic save states of a console, so the game can be run
+//! backwards. Only the most recent snapshot is stored in full. Each
+//! older snapshot is stored as the difference to the one after it:
+//! both states are XORed together, which zeroes every unchanged byte,
+//! and the runs of zeroes are compressed away.
+//!
+//! The screen is not part of save states, so each snapshot also keeps
+//! the last frame drawn, to show it again when the game goes back.
+//!
+//! When the snapshots use more memory than the budget allows, the
+//! oldest ones are dropped.
+
+use std::collections::VecDeque;
+
+use crate::screen::{NES_HEIGHT, NES_WIDTH};
+use crate::Nes;
+
+/// Memory budget used by `Rewind::default`, in bytes.
+pub const DEFAULT_REWIND_BUDGET: usize = 32 * 1024 * 1024;
+
+/// Length in bytes of the header of each run in a delta.
+const RUN_HEADER_SIZE: usize = 4;
+/// Length in bytes of the frame kept after each save state.
+const FRAME_SIZE: usize = NES_WIDTH * NES_HEIGHT * 3;
+
+#[derive(Clone, Debug)]
+pub struct Rewind {
+    /// Maximum amount of memory used by the snapshots, in bytes
+    budget: usize,
+    /// A snapshot is taken every `interval` frames
+    interval: u32,
+    /// Frames pushed since the last snapshot
+    frames: u32,
+
+    /// Most recent snapshot
+    current: Option<Vec<u8>>,
+    /// Deltas from each snapshot to the previous one, oldest first
+    deltas: VecDeque<Vec<u8>>,
+    deltas_size: usize,
+}
+
+impl Rewind {
+    /// Creates a rewind buffer that takes a snapshot every frame and
+    /// uses at most `budget` bytes.
+    pub fn new(budget: usize) -> Self {
+        Rewind {
+            budget,
+            interval: 1,
+            frames: 0,
+            current: None,
+            deltas: VecDeque::new(),
+            deltas_size: 0,
+        }
+    }
+
+    /// Takes a snapshot every `interval` frames instead of every frame.
+    /// Each step back then goes back `interval` frames.
+    pub fn with_interval(mut self, interval: u32) -> Self {
+        self.interval = interval.max(1);
+        self
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Changes the memory budget, dropping the oldest snapshots
+    /// if they no longer fit.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    /// Memory currently used by the snapshots, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.current.as_ref().map_or(0, Vec::len) + self.deltas_size
+    }
+
+    /// Amount of times `step_back` can be called.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Forgets every snapshot, e.g. after a new game is started.
+    pub fn clear(&mut self) {
+        self.frames = 0;
+        self.current = None;
+        self.deltas.clear();
+        self.deltas_size = 0;
+    }
+
+    /// Must be called once after every frame run by `nes`.
+    ///
+    /// Takes a snapshot of the console, if it is time to take one.
+    pub fn push(&mut self, nes: &Nes) {
+        self.frames += 1;
+        if self.current.is_some() && self.frames < self.interval {
+            return;
+        }
+        self.frames = 0;
+
+        let mut state = nes.save_state();
+        state.extend(nes.screen().to_rgb());
+
+        match self.current.take() {
+            // states of the same game always have the same size
+            Some(previous) if previous.len() == state.len() => {
+                let delta = encode_delta(&state, &previous);
+                self.deltas_size += delta.len();
+                self.deltas.push_back(delta);
+            }
+            _ => self.clear(),
+        }
+
+        self.current = Some(state);
+        self.evict();
+    }
+
+    /// Restores `nes` to the snapshot before the most recent one,
+    /// which is then discarded. The screen shows the restored frame.
+    ///
+    /// Returns false if there is no older snapshot. If the snapshot
+    /// can't be loaded, which happens if `nes` is running another game,
+    /// every snapshot is discarded.
+    pub fn step_back(&mut self, nes: &mut Nes) -> bool {
+        let (Some(current), Some(delta)) = (self.current.as_mut(), self.deltas.pop_back()) else {
+            return false;
+        };
+        self.deltas_size -= delta.len();
+        apply_delta(current, &delta);
+
+        let (state, frame) = current.split_at(current.len() - FRAME_SIZE);
+        if nes.load_state(state).is_err() {
+            self.clear();
+            return false;
+        }
+        nes.mut_screen().load_rgb(frame);
+
+        self.frames = 0;
+        true
+    }
+
+    /// Drops the oldest snapshots until the budget is respected.
+    fn evict(&mut self) {
+        while self.memory_usage() > self.budget {
+            match self.deltas.pop_front() {
+                Some(delta) => self.deltas_size -= delta.len(),
+                None => break,
+            }
+        }
+    }
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new(DEFAULT_REWIND_BUDGET)
+    }
+}
+
+/// Encodes the difference between two states of the same size.
+///
+/// The delta is a sequence of runs, each made of the amount of bytes
+/// that are equal in both states (16-bit), the amount of bytes that
+/// differ (16-bit), and the XOR of the differing bytes.
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let xor: Vec<u8> = from.iter().zip(to).map(|(a, b)| a ^ b).collect();
+    let mut delta = Vec::new();
+    let mut i = 0;
+
+    while i < xor.len() {
+        let equal = xor[i..]
+            .iter()
+            .take(u16::MAX as usize)
+            .take_while(|&&byte| byte == 0)
+            .count();
+        i += equal;
+
+        // short runs of equal bytes are cheaper to keep in the literal
+        // than to start a new run
+        let start = i;
+        while i < xor.len()
+            && i - start < u16::MAX as usize
+            && xor[i..].iter().take(RUN_HEADER_SIZE).any(|&byte| byte != 0)
+        {
+            i += 1;
+        }
+
+        delta.extend_from_slice(&(equal as u16).to_le_bytes());
+        delta.extend_from_slice(&((i - start) as u16).to_le_bytes());
+        delta.extend_from_slice(&xor[start..i]);
+    }
+
+    delta
+}
+
+/// Applies a delta made by `encode_delta(from, to)` to `from`, turning it into `to`.
+fn apply_delta(state: &mut [u8], delta: &[u8]) {
+    let mut i = 0;
+    let mut runs = delta;
+
+    while runs.len() >= RUN_HEADER_SIZE {
+        let equal = u16::from_le_bytes([runs[0], runs[1]]) as usize;
+        let different = u16::from_le_bytes([runs[2], runs[3]]) as usize;
+        let (literal, rest) = runs[RUN_HEADER_SIZE..].split_at(different);
+
+        i += equal;
+        for (byte, xor) in state[i..i + different].iter_mut().zip(literal) {
+            *byte ^= xor;
+        }
+        i += different;
+        runs = rest;
+    }
+}
+
+#[test]
+fn test_step_back() {
+    use crate::cartridge::Cartridge;
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    let mut rewind = Rewind::default().with_interval(2);
+    let mut states = Vec::new();
+
+    // the first frames differ, so each step back must show its own frame
+    for i in 0..9 {
+        nes.next_frame();
+        rewind.push(&nes);
+        if i % 2 == 0 {
+            states.push((nes.save_state(), nes.screen().crc32()));
+        }
+    }
+    assert_eq!(rewind.len(), 4);
+
+    for (state, screen) in states.iter().rev().skip(1) {
+        assert!(rewind.step_back(&mut nes));
+        assert_eq!(&nes.save_state(), state);
+        assert_eq!(nes.screen().crc32(), *screen);
+    }
+    assert!(!rewind.step_back(&mut nes));
+
+    // the budget only leaves room for the most recent snapshot
+    rewind.set_budget(states[0].0.len() + FRAME_SIZE);
+    nes.next_frame();
+    rewind.push(&nes);
+    assert!(rewind.is_empty());
+}
@@ -0,0 +1,193 @@
+//! Encoders that save the screen as an image.
+//!
+//! PNG images are not compressed: the pixels are stored in raw deflate
+//! blocks, which keeps the encoder small and fast. They are still read
+//! by every decoder.
+
+use std::io::{self, Write};
+
+use super::Screen;
+use crate::util;
+
+/// Rows hidden by the overscan of most NTSC TVs, at the top and at
+/// the bottom of the screen.
+pub const NTSC_OVERSCAN: usize = 8;
+
+/// Largest amount of bytes held by a raw deflate block.
+const MAX_BLOCK_SIZE: usize = 0xFFFF;
+
+/// Part of the screen saved in an image, and its scale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageOptions {
+    /// Pixels cut from the top, bottom, left and right of the screen
+    crop: [usize; 4],
+    scale: usize,
+}
+
+impl ImageOptions {
+    /// Saves the whole screen, unscaled.
+    pub fn new() -> Self {
+        ImageOptions {
+            crop: [0; 4],
+            scale: 1,
+        }
+    }
+
+    /// Cuts pixels from the top, bottom, left and right of the screen.
+    pub fn with_crop(mut self, top: usize, bottom: usize, left: usize, right: usize) -> Self {
+        self.crop = [top, bottom, left, right];
+        self
+    }
+
+    /// Cuts the rows hidden by NTSC TVs, see `NTSC_OVERSCAN`.
+    pub fn with_overscan(self) -> Self {
+        self.with_crop(NTSC_OVERSCAN, NTSC_OVERSCAN, 0, 0)
+    }
+
+    /// Repeats every pixel `scale` times horizontally and vertically.
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Screen<WIDTH, HEIGHT> {
+    /// Width and height of the image saved with `options`.
+    pub fn image_size(&self, options: ImageOptions) -> (usize, usize) {
+        let [top, bottom, left, right] = options.crop;
+        (
+            WIDTH.saturating_sub(left + right) * options.scale,
+            HEIGHT.saturating_sub(top + bottom) * options.scale,
+        )
+    }
+
+    /// Writes the draw buffer as a binary PPM image.
+    pub fn write_ppm(&self, mut writer: impl Write, options: ImageOptions) -> io::Result<()> {
+        let (width, height) = self.image_size(options);
+        write!(writer, "P6\n{width} {height}\n255\n")?;
+
+        for row in self.image_rows(options) {
+            writer.write_all(&row)?;
+        }
+        writer.flush()
+    }
+
+    /// Writes the draw buffer as a PNG image.
+    pub fn write_png(&self, mut writer: impl Write, options: ImageOptions) -> io::Result<()> {
+        let (width, height) = self.image_size(options);
+
+        // each row starts with its filter type, 0 for none
+        let mut pixels = Vec::with_capacity((width * 3 + 1) * height);
+        for row in self.image_rows(options) {
+            pixels.push(0);
+            pixels.extend_from_slice(&row);
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(width as u32).to_be_bytes());
+        header.extend_from_slice(&(height as u32).to_be_bytes());
+        // 8 bits per channel, RGB, default compression, filters and no interlacing
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        writer.write_all(b"\x89PNG\r\n\x1A\n")?;
+        write_png_chunk(&mut writer, b"IHDR", &header)?;
+        write_png_chunk(&mut writer, b"IDAT", &zlib_stored(&pixels))?;
+        write_png_chunk(&mut writer, b"IEND", &[])?;
+        writer.flush()
+    }
+
+    /// Rows of RGB bytes of the image saved with `options`.
+    fn image_rows(&self, options: ImageOptions) -> impl Iterator<Item = Vec<u8>> + '_ {
+        let [top, bottom, left, right] = options.crop;
+        let scale = options.scale;
+
+        self.draw_buffer()
+            .iter()
+            .take(HEIGHT.saturating_sub(bottom))
+            .skip(top)
+            .flat_map(move |row| {
+                let bytes: Vec<u8> = row
+                    .iter()
+                    .take(WIDTH.saturating_sub(right))
+                    .skip(left)
+                    .flat_map(|pixel| std::iter::repeat_n([pixel.r, pixel.g, pixel.b], scale))
+                    .flatten()
+                    .collect();
+                std::iter::repeat_n(bytes, scale)
+            })
+    }
+}
+
+fn write_png_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut crc_data = Vec::with_capacity(4 + data.len());
+    crc_data.extend_from_slice(kind);
+    crc_data.extend_from_slice(data);
+
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(&crc_data)?;
+    writer.write_all(&util::crc32(&crc_data).to_be_bytes())
+}
+
+/// Wraps `data` in a zlib stream made of raw deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // deflate with a 32 KB window, no preset dictionary
+    let mut stream = vec![0x78, 0x01];
+
+    // an empty stream still needs a final block
+    let count = data.len().div_ceil(MAX_BLOCK_SIZE).max(1);
+    for i in 0..count {
+        let block = &data[i * MAX_BLOCK_SIZE..((i + 1) * MAX_BLOCK_SIZE).min(data.len())];
+        let len = block.len() as u16;
+        stream.push(u8::from(i == count - 1));
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+/// Adler-32 checksum, used by zlib streams.
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+
+    let (a, b) = data.iter().fold((1, 0), |(a, b), &byte| {
+        let a = (a + byte as u32) % MOD;
+        (a, (b + a) % MOD)
+    });
+    (b << 16) | a
+}
+
+#[test]
+fn test_image_encoders() {
+    use super::pixel::Pixel;
+
+    let mut screen: Screen<3, 2> = Screen::new();
+    screen.set_pixel((0, 1), Pixel::new(1, 2, 3));
+    screen.set_pixel((1, 2), Pixel::new(4, 5, 6));
+    screen.switch_buffer();
+
+    let mut ppm = Vec::new();
+    let options = ImageOptions::new().with_crop(0, 0, 1, 0).with_scale(2);
+    screen.write_ppm(&mut ppm, options).unwrap();
+    let row1 = [1, 2, 3, 1, 2, 3, 0, 0, 0, 0, 0, 0];
+    let row2 = [0, 0, 0, 0, 0, 0, 4, 5, 6, 4, 5, 6];
+    let expected = [&b"P6\n4 4\n255\n"[..], &row1, &row1, &row2, &row2].concat();
+    assert_eq!(ppm, expected);
+
+    let mut png = Vec::new();
+    screen.write_png(&mut png, ImageOptions::new()).unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1A\n");
+    // width and height in the header
+    assert_eq!(&png[16..24], &[0, 0, 0, 3, 0, 0, 0, 2]);
+    assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xAE\x42\x60\x82");
+
+    assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+}
@@ -2,17 +2,31 @@
 //!
 //! Every pixel drawn by the PPU will be written to the screen.
 
+mod image;
 pub mod pixel;
 
 use itertools::Itertools;
 
+use crate::util;
+
 use pixel::*;
 
+pub use image::{ImageOptions, NTSC_OVERSCAN};
+
 pub const NES_WIDTH: usize = 256;
 pub const NES_HEIGHT: usize = 240;
 
 pub type NesScreen = Screen<NES_WIDTH, NES_HEIGHT>;
 
+/// One pattern table: 16x16 tiles of 8x8 pixels.
+pub type PatternTableScreen = Screen<128, 128>;
+/// The four nametables, in a 2x2 grid.
+pub type NametableScreen = Screen<{ NES_WIDTH * 2 }, { NES_HEIGHT * 2 }>;
+/// The 64 sprites of the OAM, in 8 rows of 8 cells of 8x16 pixels.
+pub type SpriteScreen = Screen<64, 128>;
+/// The 32 palette entries, in 2 rows.
+pub type PaletteScreen = Screen<16, 2>;
+
 /// Represents a doubly buffered screen.
 ///
 /// Write operations are done to the work buffer, read operations
@@ -72,6 +86,29 @@ impl<const WIDTH: usize, const HEIGHT: usize> Screen<WIDTH, HEIGHT> {
         }
     }
 
+    /// CRC-32 of the RGB bytes of the draw buffer, row by row.
+    ///
+    /// Identifies a frame, e.g. to check that a test ROM shows
+    /// the expected screen.
+    pub fn crc32(&self) -> u32 {
+        util::crc32(&self.to_rgb())
+    }
+
+    /// RGB bytes of the draw buffer, row by row.
+    pub(crate) fn to_rgb(&self) -> Vec<u8> {
+        self.flatten().flat_map(|p| [p.r, p.g, p.b]).collect()
+    }
+
+    /// Replaces the draw buffer with the RGB bytes made by `to_rgb`.
+    pub(crate) fn load_rgb(&mut self, rgb: &[u8]) {
+        let pixels = rgb
+            .chunks_exact(3)
+            .map(|rgb| Pixel::new(rgb[0], rgb[1], rgb[2]));
+        for (pixel, rgb) in self.draw_buffer_mut().iter_mut().flatten().zip(pixels) {
+            *pixel = rgb;
+        }
+    }
+
     pub fn switch_buffer(&mut self) {
         self.work = match self.work {
             WhichBuffer::One => WhichBuffer::Two,
@@ -79,6 +116,21 @@ impl<const WIDTH: usize, const HEIGHT: usize> Screen<WIDTH, HEIGHT> {
         }
     }
 
+    /// The buffer being written, e.g. the frame the PPU is drawing.
+    pub(crate) fn work_buffer(&self) -> &[[Pixel; WIDTH]; HEIGHT] {
+        match self.work {
+            WhichBuffer::One => &self.buffer1,
+            WhichBuffer::Two => &self.buffer2,
+        }
+    }
+
+    fn draw_buffer_mut(&mut self) -> &mut [[Pixel; WIDTH]; HEIGHT] {
+        match self.work {
+            WhichBuffer::One => &mut self.buffer2,
+            WhichBuffer::Two => &mut self.buffer1,
+        }
+    }
+
     fn work_buffer_mut(&mut self) -> &mut [[Pixel; WIDTH]; HEIGHT] {
         match self.work {
             WhichBuffer::One => &mut self.buffer1,
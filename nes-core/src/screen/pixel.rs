@@ -1,6 +1,6 @@
 //! Color information for the NES' PPU.
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct Pixel {
     pub r: u8,
     pub g: u8,
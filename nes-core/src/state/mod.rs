@@ -0,0 +1,248 @@
+//! Save states.
+//!
+//! A save state is a snapshot of the whole console: CPU, RAM, PPU, APU,
+//! mapper registers and cartridge RAM. The ROM itself is not part of the
+//! state. Instead, states store a checksum of the ROM, so they can only
+//! be loaded into a console running the same game.
+//!
+//! Format, with every number in little endian:
+//! * magic bytes `NESS`
+//! * format version (16-bit)
+//! * CRC-32 of the ROM (32-bit)
+//! * region of the console (8-bit)
+//! * state of each component, in a fixed order
+
+use thiserror::Error;
+
+use crate::controller::Port;
+
+const STATE_MAGIC: [u8; 4] = *b"NESS";
+
+/// Incremented every time the format changes.
+/// States from other versions are rejected.
+pub const STATE_VERSION: u16 = 6;
+
+/// Save State Error
+///
+/// - FormatError: The data is not a save state
+/// - VersionError: The state was made by another version of the format
+/// - RomMismatchError: The state was made while running another ROM
+/// - CorruptedError: The state is truncated or has extra data
+/// - DeviceMismatchError: The state was made with another input device
+///   plugged into the given port
+#[derive(Error, Debug)]
+pub enum StateError {
+    #[error("Not a save state")]
+    FormatError,
+    #[error("Unsupported save state version: {0}")]
+    VersionError(u16),
+    #[error("Save state belongs to a different ROM")]
+    RomMismatchError,
+    #[error("Save state is truncated or corrupted")]
+    CorruptedError,
+    #[error("Save state was made with another input device in port {0:?}")]
+    DeviceMismatchError(Port),
+}
+
+/// Serializes the state of the console's components.
+///
+/// Used by mappers to save their registers.
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    /// Starts a state for the ROM with the given checksum.
+    pub fn new(rom_crc32: u32) -> Self {
+        let mut writer = StateWriter { data: Vec::new() };
+        writer.write_bytes(&STATE_MAGIC);
+        writer.write_u16(STATE_VERSION);
+        writer.write_u32(rom_crc32);
+        writer
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(u8::from(value));
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i16(&mut self, value: i16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+}
+
+/// Deserializes the state of the console's components.
+///
+/// Values must be read in the same order they were written.
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    /// Checks the header of `data`, which must be a state made
+    /// for the ROM with the given checksum.
+    pub fn new(data: &'a [u8], rom_crc32: u32) -> Result<Self, StateError> {
+        let mut reader = StateReader { data };
+
+        let mut magic = [0; 4];
+        reader
+            .read_bytes(&mut magic)
+            .map_err(|_| StateError::FormatError)?;
+        if magic != STATE_MAGIC {
+            return Err(StateError::FormatError);
+        }
+
+        let version = reader.read_u16()?;
+        if version != STATE_VERSION {
+            return Err(StateError::VersionError(version));
+        }
+
+        if reader.read_u32()? != rom_crc32 {
+            return Err(StateError::RomMismatchError);
+        }
+
+        Ok(reader)
+    }
+
+    /// Makes sure the whole state was read.
+    pub fn finish(self) -> Result<(), StateError> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(StateError::CorruptedError)
+        }
+    }
+
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<(), StateError> {
+        if self.data.len() < bytes.len() {
+            return Err(StateError::CorruptedError);
+        }
+
+        let (head, tail) = self.data.split_at(bytes.len());
+        bytes.copy_from_slice(head);
+        self.data = tail;
+        Ok(())
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let mut bytes = [0; N];
+        self.read_bytes(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, StateError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::CorruptedError),
+        }
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16, StateError> {
+        Ok(i16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+}
+
+#[test]
+fn test_state_round_trip() {
+    use crate::{cartridge::Cartridge, Nes};
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    for _ in 0..10 {
+        nes.next_frame();
+    }
+
+    let state = nes.save_state();
+    for _ in 0..5 {
+        nes.next_frame();
+    }
+    let later = nes.save_state();
+
+    nes.load_state(&state).unwrap();
+    assert_eq!(nes.save_state(), state);
+    for _ in 0..5 {
+        nes.next_frame();
+    }
+    assert_eq!(nes.save_state(), later);
+
+    // a truncated state is rejected without changing the console
+    assert!(nes.load_state(&state[..state.len() - 1]).is_err());
+    assert_eq!(nes.save_state(), later);
+}
+
+#[test]
+fn test_state_device_mismatch() {
+    use crate::controller::Zapper;
+    use crate::{cartridge::Cartridge, Nes};
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    let state = nes.save_state();
+
+    nes.set_input_device(Port::Two, Some(Box::new(Zapper::new())));
+    assert!(matches!(
+        nes.load_state(&state),
+        Err(StateError::DeviceMismatchError(Port::Two))
+    ));
+}
+
+#[test]
+fn test_state_region() {
+    use crate::region::Region;
+    use crate::{cartridge::Cartridge, Nes};
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    nes.set_region(Region::Pal);
+    for _ in 0..10 {
+        nes.next_frame();
+    }
+    let state = nes.save_state();
+    nes.next_frame();
+    let later = nes.save_state();
+
+    // loading the state brings the console back to PAL timings
+    let mut ntsc = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    ntsc.load_state(&state).unwrap();
+    assert_eq!(ntsc.region(), Region::Pal);
+    assert_eq!(ntsc.save_state(), state);
+    ntsc.next_frame();
+    assert_eq!(ntsc.save_state(), later);
+}
@@ -0,0 +1,235 @@
+//! Delta modulation channel (DMC).
+
+use crate::state::{StateError, StateReader, StateWriter};
+
+/// Timer periods, in CPU cycles, indexed by the 4 bits written to $4010.
+const DMC_RATE_TABLE: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+/// Same as `DMC_RATE_TABLE`, for the slower CPU of PAL consoles.
+const DMC_RATE_TABLE_PAL: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+fn rate_table(pal: bool) -> &'static [u16; 16] {
+    if pal {
+        &DMC_RATE_TABLE_PAL
+    } else {
+        &DMC_RATE_TABLE
+    }
+}
+
+/// Delta modulation channel, mapped to $4010-$4013.
+///
+/// Plays 1-bit delta-encoded samples read directly from the CPU's
+/// memory. Since the channel cannot access the bus by itself, the
+/// [`Dmc::pending_read`] and [`Dmc::fill_buffer`] functions must be
+/// used to feed it data.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Dmc {
+    pub irq: bool,
+
+    irq_enabled: bool,
+    looping: bool,
+
+    timer: u16,
+    timer_period: u16,
+
+    /// 7-bit output level
+    level: u8,
+
+    sample_addr: u16,
+    sample_length: u16,
+    current_addr: u16,
+    bytes_remaining: u16,
+
+    buffer: Option<u8>,
+
+    shift: u8,
+    bits_remaining: u8,
+    silence: bool,
+
+    /// Uses the rates of PAL consoles
+    pal: bool,
+}
+
+impl Dmc {
+    pub fn new() -> Self {
+        Dmc {
+            timer_period: DMC_RATE_TABLE[0],
+            bits_remaining: 8,
+            silence: true,
+            ..Dmc::default()
+        }
+    }
+
+    /// Writes to one of the channel's four registers.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.irq_enabled = data & 0x80 != 0;
+                self.looping = data & 0x40 != 0;
+                self.timer_period = rate_table(self.pal)[(data & 0x0F) as usize];
+
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+            }
+            1 => self.level = data & 0x7F,
+            2 => self.sample_addr = 0xC000 | ((data as u16) << 6),
+            _ => self.sample_length = ((data as u16) << 4) | 1,
+        }
+    }
+
+    /// Switches to the rates of PAL or NTSC consoles, keeping the rate
+    /// chosen by the last write to $4010.
+    pub fn set_pal(&mut self, pal: bool) {
+        let old_table = rate_table(self.pal);
+        if let Some(index) = old_table.iter().position(|&p| p == self.timer_period) {
+            self.timer_period = rate_table(pal)[index];
+        }
+        self.pal = pal;
+    }
+
+    /// Enables or disables the channel through $4015.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    /// The channel is active while it has sample bytes left to read.
+    pub fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    /// Address the channel wants to read its next sample byte from,
+    /// if its buffer is empty.
+    pub fn pending_read(&self) -> Option<u16> {
+        if self.buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_addr)
+        } else {
+            None
+        }
+    }
+
+    /// Fills the sample buffer with the byte requested by
+    /// [`Dmc::pending_read`].
+    pub fn fill_buffer(&mut self, data: u8) {
+        self.buffer = Some(data);
+
+        // the address wraps around to 0x8000 instead of 0x0000
+        self.current_addr = if self.current_addr == 0xFFFF {
+            0x8000
+        } else {
+            self.current_addr + 1
+        };
+
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period - 1;
+
+        if !self.silence {
+            if self.shift & 0x01 != 0 {
+                if self.level <= 125 {
+                    self.level += 2;
+                }
+            } else if self.level >= 2 {
+                self.level -= 2;
+            }
+        }
+        self.shift >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            // start a new output cycle
+            self.bits_remaining = 8;
+
+            match self.buffer.take() {
+                Some(data) => {
+                    self.silence = false;
+                    self.shift = data;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    /// Current output of the channel, from 0 to 127.
+    pub fn output(&self) -> u8 {
+        self.level
+    }
+
+    fn restart(&mut self) {
+        self.current_addr = self.sample_addr;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.irq);
+        state.write_bool(self.irq_enabled);
+        state.write_bool(self.looping);
+        state.write_u16(self.timer);
+        state.write_u16(self.timer_period);
+        state.write_u8(self.level);
+        state.write_u16(self.sample_addr);
+        state.write_u16(self.sample_length);
+        state.write_u16(self.current_addr);
+        state.write_u16(self.bytes_remaining);
+        state.write_bool(self.buffer.is_some());
+        state.write_u8(self.buffer.unwrap_or(0));
+        state.write_u8(self.shift);
+        state.write_u8(self.bits_remaining);
+        state.write_bool(self.silence);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.irq = state.read_bool()?;
+        self.irq_enabled = state.read_bool()?;
+        self.looping = state.read_bool()?;
+        self.timer = state.read_u16()?;
+        self.timer_period = state.read_u16()?;
+        self.level = state.read_u8()?;
+        self.sample_addr = state.read_u16()?;
+        self.sample_length = state.read_u16()?;
+        self.current_addr = state.read_u16()?;
+        self.bytes_remaining = state.read_u16()?;
+        let buffered = state.read_bool()?;
+        let buffer = state.read_u8()?;
+        self.buffer = buffered.then_some(buffer);
+        self.shift = state.read_u8()?;
+        self.bits_remaining = state.read_u8()?;
+        self.silence = state.read_bool()?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_set_pal() {
+    let mut dmc = Dmc::new();
+    dmc.write(0x4010, 0x0F);
+    dmc.set_pal(true);
+    assert_eq!(dmc.timer_period, 50);
+    dmc.set_pal(false);
+    assert_eq!(dmc.timer_period, 54);
+}
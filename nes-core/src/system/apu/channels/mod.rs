@@ -0,0 +1,6 @@
+//! Sound channels contained in the APU.
+
+pub(crate) mod dmc;
+pub(crate) mod noise;
+pub(crate) mod pulse;
+pub(crate) mod triangle;
@@ -0,0 +1,149 @@
+//! Pseudo-random noise channel.
+
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::apu::units::{Envelope, LengthCounter};
+
+/// Timer periods, in CPU cycles, indexed by the 4 bits written to $400E.
+const NOISE_PERIOD_TABLE: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+
+/// Same as `NOISE_PERIOD_TABLE`, for the slower CPU of PAL consoles.
+const NOISE_PERIOD_TABLE_PAL: [u16; 16] = [
+    4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+];
+
+fn period_table(pal: bool) -> &'static [u16; 16] {
+    if pal {
+        &NOISE_PERIOD_TABLE_PAL
+    } else {
+        &NOISE_PERIOD_TABLE
+    }
+}
+
+/// Noise channel, mapped to $400C-$400F.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Noise {
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+
+    /// 15-bit linear feedback shift register
+    shift: u16,
+    /// Uses bit 6 instead of bit 1 as feedback, creating shorter loops
+    mode: bool,
+
+    timer: u16,
+    timer_period: u16,
+
+    /// Uses the periods of PAL consoles
+    pal: bool,
+}
+
+impl Noise {
+    pub fn new() -> Self {
+        Noise {
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            // the shift register is loaded with 1 on power-up
+            shift: 1,
+            mode: false,
+            timer: 0,
+            timer_period: NOISE_PERIOD_TABLE[0],
+            pal: false,
+        }
+    }
+
+    /// Writes to one of the channel's four registers.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.length.halt = data & 0x20 != 0;
+                self.envelope.write(data);
+            }
+            1 => { /* unused */ }
+            2 => {
+                self.mode = data & 0x80 != 0;
+                self.timer_period = period_table(self.pal)[(data & 0x0F) as usize];
+            }
+            _ => {
+                self.length.load(data >> 3);
+                self.envelope.start = true;
+            }
+        }
+    }
+
+    /// Switches to the periods of PAL or NTSC consoles, keeping the
+    /// period chosen by the last write to $400E.
+    pub fn set_pal(&mut self, pal: bool) {
+        let old_table = period_table(self.pal);
+        if let Some(index) = old_table.iter().position(|&p| p == self.timer_period) {
+            self.timer_period = period_table(pal)[index];
+        }
+        self.pal = pal;
+    }
+
+    /// Clocked every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period - 1;
+
+            let other_bit = if self.mode { 6 } else { 1 };
+            let feedback = (self.shift & 0x01) ^ ((self.shift >> other_bit) & 0x01);
+            self.shift = (self.shift >> 1) | (feedback << 14);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    /// Current output of the channel, from 0 to 15.
+    pub fn output(&self) -> u8 {
+        if !self.length.active() || self.shift & 0x01 != 0 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        self.envelope.save_state(state);
+        self.length.save_state(state);
+        state.write_u16(self.shift);
+        state.write_bool(self.mode);
+        state.write_u16(self.timer);
+        state.write_u16(self.timer_period);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.envelope.load_state(state)?;
+        self.length.load_state(state)?;
+        self.shift = state.read_u16()?;
+        self.mode = state.read_bool()?;
+        self.timer = state.read_u16()?;
+        self.timer_period = state.read_u16()?;
+        Ok(())
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_set_pal() {
+    let mut noise = Noise::new();
+    noise.write(0x400E, 0x0F);
+    noise.set_pal(true);
+    assert_eq!(noise.timer_period, 3778);
+    noise.set_pal(false);
+    assert_eq!(noise.timer_period, 4068);
+}
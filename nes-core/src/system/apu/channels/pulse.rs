@@ -0,0 +1,110 @@
+//! Pulse (square wave) channel.
+
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::apu::units::{Envelope, LengthCounter, Sweep};
+
+/// Waveforms for each of the four duty cycles.
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// Pulse channel, mapped to $4000-$4003 and $4004-$4007.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Pulse {
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    pub sweep: Sweep,
+
+    duty: u8,
+    duty_position: u8,
+
+    timer: u16,
+    timer_period: u16,
+}
+
+impl Pulse {
+    /// Creates a pulse channel. The first pulse channel negates its
+    /// sweep with one's complement.
+    pub fn new(ones_complement: bool) -> Self {
+        Pulse {
+            sweep: Sweep::new(ones_complement),
+            ..Pulse::default()
+        }
+    }
+
+    /// Writes to one of the channel's four registers.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.duty = data >> 6;
+                self.length.halt = data & 0x20 != 0;
+                self.envelope.write(data);
+            }
+            1 => self.sweep.write(data),
+            2 => {
+                self.timer_period = (self.timer_period & 0x0700) | data as u16;
+            }
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length.load(data >> 3);
+                self.duty_position = 0;
+                self.envelope.start = true;
+            }
+        }
+    }
+
+    /// Clocked every other CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.duty_position = (self.duty_position + 1) & 0x07;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+        self.sweep.clock(&mut self.timer_period);
+    }
+
+    /// Current output of the channel, from 0 to 15.
+    pub fn output(&self) -> u8 {
+        if !self.length.active()
+            || self.sweep.muting(self.timer_period)
+            || DUTY_TABLE[self.duty as usize][self.duty_position as usize] == 0
+        {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        self.envelope.save_state(state);
+        self.length.save_state(state);
+        self.sweep.save_state(state);
+        state.write_u8(self.duty);
+        state.write_u8(self.duty_position);
+        state.write_u16(self.timer);
+        state.write_u16(self.timer_period);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.envelope.load_state(state)?;
+        self.length.load_state(state)?;
+        self.sweep.load_state(state)?;
+        self.duty = state.read_u8()?;
+        self.duty_position = state.read_u8()?;
+        self.timer = state.read_u16()?;
+        self.timer_period = state.read_u16()?;
+        Ok(())
+    }
+}
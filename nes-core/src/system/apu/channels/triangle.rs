@@ -0,0 +1,118 @@
+//! Triangle wave channel.
+
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::apu::units::LengthCounter;
+
+/// The 32-step triangle waveform.
+const TRIANGLE_TABLE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
+
+/// Triangle channel, mapped to $4008-$400B.
+///
+/// Has no volume control, but contains a linear counter in
+/// addition to the length counter.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Triangle {
+    pub length: LengthCounter,
+
+    /// Also used as the halt flag of the length counter
+    control: bool,
+    linear_counter: u8,
+    linear_reload_value: u8,
+    linear_reload: bool,
+
+    sequence_position: u8,
+
+    timer: u16,
+    timer_period: u16,
+}
+
+impl Triangle {
+    /// Writes to one of the channel's four registers.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.control = data & 0x80 != 0;
+                self.length.halt = self.control;
+                self.linear_reload_value = data & 0x7F;
+            }
+            1 => { /* unused */ }
+            2 => {
+                self.timer_period = (self.timer_period & 0x0700) | data as u16;
+            }
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length.load(data >> 3);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+
+            if self.length.active() && self.linear_counter > 0 {
+                self.sequence_position = (self.sequence_position + 1) & 0x1F;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    /// Current output of the channel, from 0 to 15.
+    ///
+    /// Silencing the channel freezes the sequencer instead of setting
+    /// the output to 0, so the last value is held.
+    pub fn output(&self) -> u8 {
+        if self.timer_period < 2 {
+            // ultrasonic frequencies, some games use them to silence the
+            // channel; output the middle of the waveform to avoid popping
+            7
+        } else {
+            TRIANGLE_TABLE[self.sequence_position as usize]
+        }
+    }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        self.length.save_state(state);
+        state.write_bool(self.control);
+        state.write_u8(self.linear_counter);
+        state.write_u8(self.linear_reload_value);
+        state.write_bool(self.linear_reload);
+        state.write_u8(self.sequence_position);
+        state.write_u16(self.timer);
+        state.write_u16(self.timer_period);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.length.load_state(state)?;
+        self.control = state.read_bool()?;
+        self.linear_counter = state.read_u8()?;
+        self.linear_reload_value = state.read_u8()?;
+        self.linear_reload = state.read_bool()?;
+        self.sequence_position = state.read_u8()?;
+        self.timer = state.read_u16()?;
+        self.timer_period = state.read_u16()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Mixing and filtering of the APU's channels.
+
+/// Combines the output of the channels into a single value between
+/// 0.0 and 1.0, using the approximation of the NES' nonlinear mixer.
+pub(crate) fn mix(pulse1: u8, pulse2: u8, triangle: u8, noise: u8, dmc: u8) -> f32 {
+    let pulse_sum = (pulse1 + pulse2) as f32;
+    let pulse_out = if pulse_sum == 0.0 {
+        0.0
+    } else {
+        95.88 / (8128.0 / pulse_sum + 100.0)
+    };
+
+    let tnd_sum = triangle as f32 / 8227.0 + noise as f32 / 12241.0 + dmc as f32 / 22638.0;
+    let tnd_out = if tnd_sum == 0.0 {
+        0.0
+    } else {
+        159.79 / (1.0 / tnd_sum + 100.0)
+    };
+
+    pulse_out + tnd_out
+}
+
+/// First-order filter.
+///
+/// The NES' audio output passes through two high-pass filters
+/// and one low-pass filter before reaching the speakers.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Filter {
+    kind: FilterKind,
+    alpha: f32,
+    prev_input: f32,
+    prev_output: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum FilterKind {
+    HighPass,
+    LowPass,
+}
+
+impl Filter {
+    pub fn new(kind: FilterKind, cutoff: f32, sample_rate: f32) -> Self {
+        let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+        let dt = 1.0 / sample_rate;
+
+        let alpha = match kind {
+            FilterKind::HighPass => rc / (rc + dt),
+            FilterKind::LowPass => dt / (rc + dt),
+        };
+
+        Filter {
+            kind,
+            alpha,
+            prev_input: 0.0,
+            prev_output: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = match self.kind {
+            FilterKind::HighPass => self.alpha * (self.prev_output + input - self.prev_input),
+            FilterKind::LowPass => self.prev_output + self.alpha * (input - self.prev_output),
+        };
+
+        self.prev_input = input;
+        self.prev_output = output;
+        output
+    }
+}
+
+/// The filter chain found on the NES.
+pub(crate) fn nes_filters(sample_rate: f32) -> [Filter; 3] {
+    [
+        Filter::new(FilterKind::HighPass, 90.0, sample_rate),
+        Filter::new(FilterKind::HighPass, 440.0, sample_rate),
+        Filter::new(FilterKind::LowPass, 14000.0, sample_rate),
+    ]
+}
@@ -0,0 +1,365 @@
+//! Module for the Audio Processing Unit.
+//!
+//! The APU is part of the same chip as the CPU and contains five
+//! sound channels: two pulse waves, a triangle wave, noise and a
+//! delta modulation channel (DMC), which plays samples.
+//!
+//! The channels are controlled by the frame counter, which clocks
+//! their envelopes, sweeps and length counters about 240 times per
+//! second and may send interrupt requests to the CPU.
+
+mod channels;
+mod mixer;
+mod units;
+
+use std::collections::VecDeque;
+
+use crate::region::Region;
+use crate::state::{StateError, StateReader, StateWriter};
+use channels::{dmc::Dmc, noise::Noise, pulse::Pulse, triangle::Triangle};
+use mixer::Filter;
+
+/// Start of the channel registers
+pub const APU_ADDR_START: u16 = 0x4000;
+/// End of the channel registers
+pub const APU_ADDR_END: u16 = 0x4013;
+/// Enables the channels on writes, reports their status on reads
+pub const APU_STATUS_ADDR: u16 = 0x4015;
+/// Configures the frame counter. Only writable.
+pub const APU_FRAME_COUNTER_ADDR: u16 = 0x4017;
+
+/// Sample rate used until the user picks one.
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+/// CPU cycles on which the frame counter clocks the channels.
+/// The last step is only used in the 5-step mode.
+const FRAME_STEPS: [u32; 5] = [7457, 14913, 22371, 29829, 37281];
+/// Same as `FRAME_STEPS`, for the slower CPU of PAL consoles.
+const FRAME_STEPS_PAL: [u32; 5] = [8313, 16627, 24939, 33253, 41565];
+
+#[derive(Clone, Debug)]
+pub struct Apu {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    pub(crate) dmc: Dmc,
+
+    /// Uses the 5-step sequence instead of the 4-step one.
+    five_step_mode: bool,
+    irq_inhibit: bool,
+    frame_irq: bool,
+    /// CPU cycles since the start of the frame counter's sequence
+    frame_cycle: u32,
+
+    /// The pulse channels are clocked every other CPU cycle
+    odd_cycle: bool,
+
+    /// Sets the speed of the CPU and the timings of the channels
+    region: Region,
+
+    sample_rate: u32,
+    /// Amount of CPU cycles to be averaged into each sample
+    cycles_per_sample: f64,
+    sample_cycles: f64,
+    sample_sum: f32,
+    sample_count: u32,
+    filters: [Filter; 3],
+    samples: VecDeque<f32>,
+    /// Copy of the samples kept for a `Recorder`, taken once per frame
+    pub(crate) recorded_samples: Option<Vec<f32>>,
+}
+
+impl Apu {
+    pub fn new() -> Apu {
+        Apu {
+            pulse1: Pulse::new(true),
+            pulse2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::new(),
+            dmc: Dmc::new(),
+
+            five_step_mode: false,
+            irq_inhibit: false,
+            frame_irq: false,
+            frame_cycle: 0,
+
+            odd_cycle: false,
+
+            region: Region::Ntsc,
+
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            cycles_per_sample: Region::Ntsc.cpu_frequency() / DEFAULT_SAMPLE_RATE as f64,
+            sample_cycles: 0.0,
+            sample_sum: 0.0,
+            sample_count: 0,
+            filters: mixer::nes_filters(DEFAULT_SAMPLE_RATE as f32),
+            samples: VecDeque::new(),
+            recorded_samples: None,
+        }
+    }
+
+    /// Silences all channels and restarts the frame counter,
+    /// as done by the reset button.
+    pub fn reset(&mut self) {
+        self.cpu_write(APU_STATUS_ADDR, 0x00);
+        self.frame_irq = false;
+        self.frame_cycle = 0;
+        self.odd_cycle = false;
+    }
+
+    /// Changes the speed of the CPU and the timings of the channels.
+    ///
+    /// Samples that were already generated are discarded.
+    pub fn set_region(&mut self, region: Region) {
+        // Dendy consoles keep the timings of NTSC consoles
+        let pal = region == Region::Pal;
+        self.noise.set_pal(pal);
+        self.dmc.set_pal(pal);
+
+        self.region = region;
+        self.set_sample_rate(self.sample_rate);
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Changes the rate at which samples are generated.
+    ///
+    /// Samples that were already generated are discarded.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        let sample_rate = sample_rate.max(1);
+
+        self.sample_rate = sample_rate;
+        self.cycles_per_sample = self.region.cpu_frequency() / sample_rate as f64;
+        self.sample_cycles = 0.0;
+        self.sample_sum = 0.0;
+        self.sample_count = 0;
+        self.filters = mixer::nes_filters(sample_rate as f32);
+        self.samples.clear();
+    }
+
+    /// Takes all samples generated since the last call.
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        self.samples.drain(..).collect()
+    }
+
+    /// The frame counter and the DMC may request interrupts.
+    pub fn irq_pending(&self) -> bool {
+        self.frame_irq || self.dmc.irq
+    }
+
+    pub fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4000..=0x4003 => self.pulse1.write(addr, data),
+            0x4004..=0x4007 => self.pulse2.write(addr, data),
+            0x4008..=0x400B => self.triangle.write(addr, data),
+            0x400C..=0x400F => self.noise.write(addr, data),
+            0x4010..=0x4013 => self.dmc.write(addr, data),
+            APU_STATUS_ADDR => {
+                self.pulse1.length.set_enabled(data & 0x01 != 0);
+                self.pulse2.length.set_enabled(data & 0x02 != 0);
+                self.triangle.length.set_enabled(data & 0x04 != 0);
+                self.noise.length.set_enabled(data & 0x08 != 0);
+                self.dmc.set_enabled(data & 0x10 != 0);
+            }
+            APU_FRAME_COUNTER_ADDR => {
+                self.five_step_mode = data & 0x80 != 0;
+                self.irq_inhibit = data & 0x40 != 0;
+
+                if self.irq_inhibit {
+                    self.frame_irq = false;
+                }
+
+                self.frame_cycle = 0;
+                if self.five_step_mode {
+                    // the 5-step mode immediately clocks all units
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Reads the status register ($4015).
+    ///
+    /// This function is mutable because reading the status
+    /// acknowledges the frame counter's interrupt.
+    pub fn read_status(&mut self) -> u8 {
+        let data = self.peek_status();
+        self.frame_irq = false;
+        data
+    }
+
+    /// Value of the status register, without acknowledging the
+    /// frame counter's interrupt.
+    pub fn peek_status(&self) -> u8 {
+        u8::from(self.pulse1.length.active())
+            | u8::from(self.pulse2.length.active()) << 1
+            | u8::from(self.triangle.length.active()) << 2
+            | u8::from(self.noise.length.active()) << 3
+            | u8::from(self.dmc.active()) << 4
+            | u8::from(self.frame_irq) << 6
+            | u8::from(self.dmc.irq) << 7
+    }
+
+    /// **APU clock cycle**
+    ///
+    /// Must be called once every CPU cycle.
+    pub fn clock(&mut self) {
+        self.clock_frame_counter();
+
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+
+        if self.odd_cycle {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+
+        self.generate_sample();
+    }
+
+    fn clock_frame_counter(&mut self) {
+        self.frame_cycle += 1;
+
+        let [step1, step2, step3, step4, step5] = match self.region {
+            Region::Pal => FRAME_STEPS_PAL,
+            Region::Ntsc | Region::Dendy => FRAME_STEPS,
+        };
+
+        match self.frame_cycle {
+            cycle if cycle == step1 || cycle == step3 => {
+                self.clock_quarter_frame();
+            }
+            cycle if cycle == step2 => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            }
+            cycle if cycle == step4 && !self.five_step_mode => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+
+                if !self.irq_inhibit {
+                    self.frame_irq = true;
+                }
+                self.frame_cycle = 0;
+            }
+            cycle if cycle == step5 && self.five_step_mode => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+                self.frame_cycle = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Clocks the envelopes and the triangle's linear counter.
+    fn clock_quarter_frame(&mut self) {
+        self.pulse1.clock_quarter_frame();
+        self.pulse2.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+    }
+
+    /// Clocks the length counters and sweeps.
+    fn clock_half_frame(&mut self) {
+        self.pulse1.clock_half_frame();
+        self.pulse2.clock_half_frame();
+        self.triangle.clock_half_frame();
+        self.noise.clock_half_frame();
+    }
+
+    /// Averages the mixed output of every CPU cycle into samples
+    /// at the chosen sample rate.
+    fn generate_sample(&mut self) {
+        self.sample_sum += mixer::mix(
+            self.pulse1.output(),
+            self.pulse2.output(),
+            self.triangle.output(),
+            self.noise.output(),
+            self.dmc.output(),
+        );
+        self.sample_count += 1;
+
+        self.sample_cycles += 1.0;
+        if self.sample_cycles < self.cycles_per_sample {
+            return;
+        }
+        self.sample_cycles -= self.cycles_per_sample;
+
+        let mut sample = self.sample_sum / self.sample_count as f32;
+        for filter in self.filters.iter_mut() {
+            sample = filter.process(sample);
+        }
+
+        self.sample_sum = 0.0;
+        self.sample_count = 0;
+
+        // keeps at most one second of samples if nobody takes them
+        if self.samples.len() >= self.sample_rate as usize {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        if let Some(recorded) = &mut self.recorded_samples {
+            recorded.push(sample);
+        }
+    }
+
+    /// Writes the state of the channels and frame counter to a save state.
+    ///
+    /// Samples and the output filters are not saved.
+    pub fn save_state(&self, state: &mut StateWriter) {
+        self.pulse1.save_state(state);
+        self.pulse2.save_state(state);
+        self.triangle.save_state(state);
+        self.noise.save_state(state);
+        self.dmc.save_state(state);
+
+        state.write_bool(self.five_step_mode);
+        state.write_bool(self.irq_inhibit);
+        state.write_bool(self.frame_irq);
+        state.write_u32(self.frame_cycle);
+        state.write_bool(self.odd_cycle);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.pulse1.load_state(state)?;
+        self.pulse2.load_state(state)?;
+        self.triangle.load_state(state)?;
+        self.noise.load_state(state)?;
+        self.dmc.load_state(state)?;
+
+        self.five_step_mode = state.read_bool()?;
+        self.irq_inhibit = state.read_bool()?;
+        self.frame_irq = state.read_bool()?;
+        self.frame_cycle = state.read_u32()?;
+        self.odd_cycle = state.read_bool()?;
+        Ok(())
+    }
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_frame_irq() {
+    let mut apu = Apu::new();
+    apu.cpu_write(APU_FRAME_COUNTER_ADDR, 0x00);
+
+    for _ in 0..FRAME_STEPS[3] {
+        apu.clock();
+    }
+    assert!(apu.irq_pending());
+
+    // reading the status acknowledges the interrupt
+    assert_eq!(apu.read_status() & 0x40, 0x40);
+    assert!(!apu.irq_pending());
+}
@@ -0,0 +1,227 @@
+//! Units shared by the APU channels.
+//!
+//! The envelope, length counter and sweep units are clocked by the
+//! frame counter and control the volume, duration and pitch of the
+//! channels that contain them.
+
+use crate::state::{StateError, StateReader, StateWriter};
+
+/// Lookup table used when loading the length counter.
+///
+/// Indexed by the 5 bits written to the upper part of the channels'
+/// fourth register.
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/// Volume envelope, used by the pulse and noise channels.
+///
+/// Generates either a constant volume or a decreasing saw envelope
+/// that may loop.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Envelope {
+    pub start: bool,
+    pub looping: bool,
+    pub constant: bool,
+    /// Constant volume or period of the divider, depending on `constant`
+    pub volume: u8,
+
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    /// Sets the envelope from the lower 6 bits of a `--LC VVVV` register.
+    pub fn write(&mut self, data: u8) {
+        self.looping = data & 0x20 != 0;
+        self.constant = data & 0x10 != 0;
+        self.volume = data & 0x0F;
+    }
+
+    /// Clocked by the quarter frames of the frame counter.
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant {
+            self.volume
+        } else {
+            self.decay
+        }
+    }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.start);
+        state.write_bool(self.looping);
+        state.write_bool(self.constant);
+        state.write_u8(self.volume);
+        state.write_u8(self.divider);
+        state.write_u8(self.decay);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.start = state.read_bool()?;
+        self.looping = state.read_bool()?;
+        self.constant = state.read_bool()?;
+        self.volume = state.read_u8()?;
+        self.divider = state.read_u8()?;
+        self.decay = state.read_u8()?;
+        Ok(())
+    }
+}
+
+/// Length counter, which silences a channel after a given amount of
+/// half frames.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct LengthCounter {
+    pub enabled: bool,
+    pub halt: bool,
+    pub counter: u8,
+}
+
+impl LengthCounter {
+    /// Loads the counter from the 5-bit index into the length table.
+    ///
+    /// Does nothing if the channel is disabled through $4015.
+    pub fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    /// Enables or disables the channel. Disabling it clears the counter.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    /// Clocked by the half frames of the frame counter.
+    pub fn clock(&mut self) {
+        if !self.halt && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.counter > 0
+    }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.enabled);
+        state.write_bool(self.halt);
+        state.write_u8(self.counter);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.enabled = state.read_bool()?;
+        self.halt = state.read_bool()?;
+        self.counter = state.read_u8()?;
+        Ok(())
+    }
+}
+
+/// Sweep unit, which periodically adjusts the period of a pulse channel.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Sweep {
+    pub enabled: bool,
+    pub negate: bool,
+    pub period: u8,
+    pub shift: u8,
+    pub reload: bool,
+
+    /// The first pulse channel uses one's complement when negating,
+    /// while the second one uses two's complement.
+    pub ones_complement: bool,
+
+    divider: u8,
+}
+
+impl Sweep {
+    pub fn new(ones_complement: bool) -> Self {
+        Sweep {
+            ones_complement,
+            ..Sweep::default()
+        }
+    }
+
+    /// Sets the sweep from a `EPPP NSSS` register.
+    pub fn write(&mut self, data: u8) {
+        self.enabled = data & 0x80 != 0;
+        self.period = (data >> 4) & 0x07;
+        self.negate = data & 0x08 != 0;
+        self.shift = data & 0x07;
+        self.reload = true;
+    }
+
+    /// Period the channel's timer would be changed to.
+    pub fn target(&self, timer_period: u16) -> u16 {
+        let change = timer_period >> self.shift;
+
+        if self.negate {
+            let change = change + u16::from(self.ones_complement);
+            timer_period.saturating_sub(change)
+        } else {
+            timer_period + change
+        }
+    }
+
+    /// A channel is muted when its period is too small or when the
+    /// sweep's target period overflows 11 bits, even if the sweep
+    /// is disabled.
+    pub fn muting(&self, timer_period: u16) -> bool {
+        timer_period < 8 || self.target(timer_period) > 0x7FF
+    }
+
+    /// Clocked by the half frames of the frame counter.
+    ///
+    /// May change the timer period of the channel.
+    pub fn clock(&mut self, timer_period: &mut u16) {
+        if self.divider == 0 && self.enabled && self.shift > 0 && !self.muting(*timer_period) {
+            *timer_period = self.target(*timer_period);
+        }
+
+        if self.divider == 0 || self.reload {
+            self.divider = self.period;
+            self.reload = false;
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.enabled);
+        state.write_bool(self.negate);
+        state.write_u8(self.period);
+        state.write_u8(self.shift);
+        state.write_bool(self.reload);
+        state.write_u8(self.divider);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.enabled = state.read_bool()?;
+        self.negate = state.read_bool()?;
+        self.period = state.read_u8()?;
+        self.shift = state.read_u8()?;
+        self.reload = state.read_bool()?;
+        self.divider = state.read_u8()?;
+        Ok(())
+    }
+}
@@ -4,27 +4,53 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::cartridge::Cartridge;
-use crate::controller::{Controller, CTRL_ADDR_END, CTRL_ADDR_START};
+use crate::controller::{
+    Controller, InputDevice, Port, StandardController, CTRL_ADDR_END, CTRL_ADDR_START,
+    CTRL_DATA_MASK, NO_DEVICE_ID,
+};
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::apu::{
+    Apu, APU_ADDR_END, APU_ADDR_START, APU_FRAME_COUNTER_ADDR, APU_STATUS_ADDR,
+};
 use crate::system::ppu::{dma::Dma, Ppu, PPU_ADDR_END, PPU_ADDR_START};
 use crate::system::ram::{Ram, RAM_ADDR_END, RAM_ADDR_START, RAM_MIRROR};
 
 use super::ppu::dma::DMA_ADDR;
 
 /// Contains the possible devices connected to the CPU.
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct Bus {
     /// The console's Picture Processing Unit
     pub ppu: Ppu,
     /// Random Access Memory, 2 kb size with mirrorring up to 8 kb
     pub ram: Ram,
+    /// The console's Audio Processing Unit
+    pub apu: Apu,
 
+    /// Buttons held on the devices of the controller ports
     pub controllers: [Controller; 2],
-    controller_state: [Controller; 2],
+    /// Devices plugged into the controller ports and the expansion port
+    pub(crate) devices: [Option<Box<dyn InputDevice>>; 3],
+    /// Last value seen on the data bus, returned by the bits
+    /// that no device drives
+    open_bus: u8,
 
     pub(crate) cartridge: Option<Rc<RefCell<Cartridge>>>,
 
     /// Allows the PPU to have direct access to memory
     pub(crate) dma: Dma,
+
+    /// Reads and writes, only recorded while the debugger
+    /// watches memory
+    pub(crate) accesses: Option<Vec<MemoryAccess>>,
+}
+
+/// A read or a write made through the bus.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MemoryAccess {
+    pub addr: u16,
+    pub value: u8,
+    pub write: bool,
 }
 
 impl Bus {
@@ -32,12 +58,20 @@ impl Bus {
         Bus {
             ppu: Ppu::new(),
             ram: Ram::default(),
+            apu: Apu::new(),
 
             controllers: [Controller::empty(); 2],
-            controller_state: [Controller::empty(); 2],
+            devices: [
+                Some(Box::new(StandardController::new())),
+                Some(Box::new(StandardController::new())),
+                None,
+            ],
+            open_bus: 0,
 
             cartridge: None,
             dma: Dma::new(),
+
+            accesses: None,
         }
     }
 
@@ -48,11 +82,79 @@ impl Bus {
     }
 
     pub fn reset(&mut self) {
+        if let Some(cart) = &self.cartridge {
+            cart.borrow_mut().reset();
+        }
         self.ppu.reset();
+        self.apu.reset();
         self.dma = Dma::default();
     }
 
+    /// Writes the state of every device connected to the bus,
+    /// including the cartridge, to a save state.
+    pub fn save_state(&self, state: &mut StateWriter) {
+        self.ram.save_state(state);
+        for controller in &self.controllers {
+            state.write_u8(controller.bits());
+        }
+        for device in &self.devices {
+            // tells which kind of device the state belongs to
+            state.write_u8(
+                device
+                    .as_ref()
+                    .map_or(NO_DEVICE_ID, |device| device.state_id()),
+            );
+            if let Some(device) = device {
+                device.save_state(state);
+            }
+        }
+        state.write_u8(self.open_bus);
+        self.dma.save_state(state);
+
+        self.ppu.save_state(state);
+        self.apu.save_state(state);
+        if let Some(cart) = &self.cartridge {
+            cart.borrow().save_state(state);
+        }
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.ram.load_state(state)?;
+        for controller in self.controllers.iter_mut() {
+            *controller = Controller::from_bits_truncate(state.read_u8()?);
+        }
+        for (port, device) in Port::ALL.into_iter().zip(&mut self.devices) {
+            let id = device
+                .as_ref()
+                .map_or(NO_DEVICE_ID, |device| device.state_id());
+            if state.read_u8()? != id {
+                return Err(StateError::DeviceMismatchError(port));
+            }
+            if let Some(device) = device {
+                device.load_state(state)?;
+            }
+        }
+        self.open_bus = state.read_u8()?;
+        self.dma.load_state(state)?;
+
+        self.ppu.load_state(state)?;
+        self.apu.load_state(state)?;
+        if let Some(cart) = &self.cartridge {
+            cart.borrow_mut().load_state(state)?;
+        }
+        Ok(())
+    }
+
     pub fn write(&mut self, addr: u16, data: u8) {
+        if let Some(accesses) = &mut self.accesses {
+            accesses.push(MemoryAccess {
+                addr,
+                value: data,
+                write: true,
+            });
+        }
+        self.open_bus = data;
+
         {
             // scope for `cart`, allows the mutable borrow to end before
             // the ppu also tries to borrow `cart`
@@ -63,7 +165,7 @@ impl Bus {
                 .expect("No cartridge inserted!")
                 .borrow_mut();
 
-            if let Some(_mapped_data) = cart.cpu_map_write(addr, data) {
+            if cart.cpu_map_write(addr, data) {
                 return;
             }
         }
@@ -82,15 +184,38 @@ impl Bus {
                 self.dma.addr = 0x00;
                 self.dma.transfer = true;
             }
-            CTRL_ADDR_START..=CTRL_ADDR_END => {
-                let which = addr as usize & 0x1;
-                self.controller_state[which] = self.controllers[which];
+            APU_ADDR_START..=APU_ADDR_END | APU_STATUS_ADDR | APU_FRAME_COUNTER_ADDR => {
+                self.apu.cpu_write(addr, data);
+            }
+            CTRL_ADDR_START => {
+                // every device sees the outputs of $4016, e.g. the strobe
+                for (port, device) in Port::ALL.into_iter().zip(&mut self.devices) {
+                    if let Some(device) = device {
+                        if let Some(&buttons) = self.controllers.get(port as usize) {
+                            device.set_buttons(buttons);
+                        }
+                        device.write(data);
+                    }
+                }
             }
             _ => {} // _ => panic!("invalid address used to write to RAM: {:#4X}", addr), // TODO: should panic?
         }
     }
 
     pub fn read(&mut self, addr: u16) -> u8 {
+        let data = self.read_data(addr);
+        self.open_bus = data;
+        if let Some(accesses) = &mut self.accesses {
+            accesses.push(MemoryAccess {
+                addr,
+                value: data,
+                write: false,
+            });
+        }
+        data
+    }
+
+    fn read_data(&mut self, addr: u16) -> u8 {
         let cart = self
             .cartridge
             .as_ref()
@@ -111,12 +236,19 @@ impl Bus {
                 // & 0x07 mirrors into 8 entries:
                 self.ppu.cpu_read(addr & 0x07)
             }
+            APU_STATUS_ADDR => self.apu.read_status(),
             CTRL_ADDR_START..=CTRL_ADDR_END => {
-                let which = addr as usize & 0x1;
-                let data = u8::from(self.controller_state[which].bits() & 0x80 > 0);
-                self.controller_state[which] =
-                    Controller::from_bits_truncate(self.controller_state[which].bits() << 1);
-                data
+                let mut data = 0;
+                for (port, device) in Port::ALL.into_iter().zip(&mut self.devices) {
+                    if let Some(device) = device.as_mut().filter(|_| port.reads(addr)) {
+                        if let Some(&buttons) = self.controllers.get(port as usize) {
+                            device.set_buttons(buttons);
+                        }
+                        device.sense_light(&self.ppu.beam());
+                        data |= device.read(addr);
+                    }
+                }
+                (data & CTRL_DATA_MASK) | (self.open_bus & !CTRL_DATA_MASK)
             }
             // TODO: find out if this should panic or not
             _ => 0,
@@ -124,15 +256,85 @@ impl Bus {
         }
     }
 
-    pub fn treat_dma_transfer(&mut self, clock_counter: u32) -> bool {
+    /// Reads from the bus without side effects: PPU and APU registers
+    /// are not acknowledged and the controllers are not shifted.
+    ///
+    /// Registers that can't be read return 0.
+    pub fn peek(&self, addr: u16) -> u8 {
+        if let Some(cart) = &self.cartridge {
+            if let Some(mapped_data) = cart.borrow().cpu_map_read(addr) {
+                return mapped_data;
+            }
+        }
+
+        match addr {
+            RAM_ADDR_START..=RAM_ADDR_END => self.ram.read_mirrored(addr, RAM_MIRROR),
+            PPU_ADDR_START..=PPU_ADDR_END => self.ppu.cpu_peek(addr & 0x07),
+            APU_STATUS_ADDR => self.apu.peek_status(),
+            CTRL_ADDR_START..=CTRL_ADDR_END => {
+                let data = Port::ALL
+                    .into_iter()
+                    .zip(&self.devices)
+                    .filter(|(port, _)| port.reads(addr))
+                    .filter_map(|(_, device)| device.as_ref())
+                    .fold(0, |data, device| data | device.peek(addr));
+                (data & CTRL_DATA_MASK) | (self.open_bus & !CTRL_DATA_MASK)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Writes the CPU's RAM or the cartridge's program RAM, without
+    /// side effects on registers.
+    ///
+    /// Returns false if `addr` isn't mapped to RAM.
+    pub fn poke(&mut self, addr: u16, data: u8) -> bool {
+        if let Some(cart) = &self.cartridge {
+            if cart.borrow_mut().cpu_poke(addr, data) {
+                return true;
+            }
+        }
+
+        match addr {
+            RAM_ADDR_START..=RAM_ADDR_END => {
+                self.ram.write_mirrored(addr, data, RAM_MIRROR);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Clocks the APU, feeding the DMC with the sample bytes it
+    /// requests from memory.
+    pub fn clock_apu(&mut self) {
+        self.apu.clock();
+
+        if let Some(addr) = self.apu.dmc.pending_read() {
+            let data = self.read(addr);
+            self.apu.dmc.fill_buffer(data);
+        }
+    }
+
+    /// Interrupt requests that may be sent to the CPU, either
+    /// by the APU or by the cartridge.
+    pub fn irq_pending(&self) -> bool {
+        let cart_irq = self
+            .cartridge
+            .as_ref()
+            .is_some_and(|cart| cart.borrow().irq_pending());
+
+        self.apu.irq_pending() || cart_irq
+    }
+
+    pub fn treat_dma_transfer(&mut self, cpu_cycle: u64) -> bool {
         if self.dma.transfer {
             if self.dma.dummy {
                 // waiting to synchronise the CPU to the DMA
-                if clock_counter % 2 == 1 {
+                if cpu_cycle % 2 == 1 {
                     // synchronise!
                     self.dma.dummy = false;
                 }
-            } else if clock_counter % 2 == 0 {
+            } else if cpu_cycle % 2 == 0 {
                 // on even cycles, read data from the CPU address space
                 self.dma.data = self.read((self.dma.page as u16) << 8 | self.dma.addr as u16);
             } else {
@@ -153,8 +355,67 @@ impl Bus {
     }
 }
 
+impl Clone for Bus {
+    /// Clones the bus along with its own copy of the cartridge, so that
+    /// both buses don't share the cartridge's RAM and mapper registers.
+    fn clone(&self) -> Self {
+        let mut bus = Bus {
+            ppu: self.ppu.clone(),
+            ram: self.ram.clone(),
+            apu: self.apu.clone(),
+
+            controllers: self.controllers,
+            devices: self.devices.clone(),
+            open_bus: self.open_bus,
+
+            cartridge: None,
+            dma: self.dma,
+
+            accesses: None,
+        };
+
+        if let Some(cart) = &self.cartridge {
+            bus.insert_cartridge(cart.borrow().clone());
+        }
+        bus
+    }
+}
+
 impl Default for Bus {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+use crate::cartridge::ines_rom;
+
+#[test]
+fn test_clone_has_own_cartridge() {
+    let mut bus = Bus::new();
+    bus.insert_cartridge(Cartridge::from_bytes(&ines_rom(0, 1, 1, 0)).unwrap());
+
+    let mut clone = bus.clone();
+    clone.write(0x6000, 0x42);
+
+    assert_eq!(clone.read(0x6000), 0x42);
+    assert_eq!(bus.read(0x6000), 0x00);
+}
+
+#[test]
+fn test_controller_ports_open_bus() {
+    let mut bus = Bus::new();
+    bus.insert_cartridge(Cartridge::from_bytes(&ines_rom(0, 1, 1, 0)).unwrap());
+    bus.controllers[0] = Controller::BUTTON_A;
+    bus.write(CTRL_ADDR_START, 1);
+    bus.write(CTRL_ADDR_START, 0);
+
+    // the upper bits keep the last value seen on the bus
+    bus.write(0x0000, 0x40);
+    assert_eq!(bus.read(CTRL_ADDR_START), 0x41);
+    assert_eq!(bus.read(CTRL_ADDR_START), 0x40);
+
+    bus.devices[Port::Two as usize] = None;
+    assert_eq!(bus.read(CTRL_ADDR_END), 0x40);
+    assert_eq!(bus.peek(CTRL_ADDR_END), 0x40);
+}
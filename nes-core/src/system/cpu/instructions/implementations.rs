@@ -33,6 +33,44 @@ impl Cpu {
         self.pc = self.data.addr_abs;
     }
 
+    /// Helper function. Adds `value` and the carry bit to the
+    /// Accumulator, as described in [`Cpu::adc`].
+    fn add_with_carry(&mut self, value: u8) {
+        // 0 or 1
+        let c = u8::from(self.status.contains(CpuFlags::C));
+
+        // Impossible for addition below to overflow on our machine
+        // since the result is `u16`, we will later check if the addition
+        // overflowed considering only the lower 8 bits
+        let addition = self.a as u16 + value as u16 + c as u16;
+
+        // If the result is over 0xFF, a carry bit is needed
+        self.status.set(CpuFlags::C, addition > 0xFF);
+
+        // If the 8 bit addition results in 0x00, the Z flag is set to 1
+        // We need to remove the higher 8 bits of the 16-bit addition
+        self.status.set(CpuFlags::Z, addition & 0x00FF == 0);
+
+        // If the most significant bit of the 8-bit addition is 1,
+        // the result may be negative if it is treated like so. The N
+        // flag will be set to 1
+        self.status.set(CpuFlags::N, addition & 0x0080 != 0);
+
+        // * If the accumulator and the memory are positive and the result is
+        // negative, an overflow happened.
+        // * If the accumulator and the memory are negative and the result is
+        // positive, an overflow happened.
+        // * Otherwise, no overflow happened.
+        let acc_pos = self.a & 0x80 == 0;
+        let mem_pos = value & 0x80 == 0;
+        let res_pos = addition & 0x80 == 0;
+        let overflow = (acc_pos && mem_pos && !res_pos) || (!acc_pos && !mem_pos && res_pos);
+        self.status.set(CpuFlags::V, overflow);
+
+        // Set the accumulator to the 8-bit result of the addition
+        self.a = (addition & 0x00FF) as u8;
+    }
+
     /// Add memory to Accumulator with Carry
     ///
     /// Adds the accumulator to the value in memory.
@@ -67,40 +105,7 @@ impl Cpu {
     /// * Otherwise, no overflow happened.
     pub fn adc(&mut self) -> u8 {
         self.fetch();
-
-        // 0 or 1
-        let c = u8::from(self.status.contains(CpuFlags::C));
-
-        // Impossible for addition below to overflow on our machine
-        // since the result is `u16`, we will later check if the addition
-        // overflowed considering only the lower 8 bits
-        let addition = self.a as u16 + self.data.fetched as u16 + c as u16;
-
-        // If the result is over 0xFF, a carry bit is needed
-        self.status.set(CpuFlags::C, addition > 0xFF);
-
-        // If the 8 bit addition results in 0x00, the Z flag is set to 1
-        // We need to remove the higher 8 bits of the 16-bit addition
-        self.status.set(CpuFlags::Z, addition & 0x00FF == 0);
-
-        // If the most significant bit of the 8-bit addition is 1,
-        // the result may be negative if it is treated like so. The N
-        // flag will be set to 1
-        self.status.set(CpuFlags::N, addition & 0x0080 != 0);
-
-        // * If the accumulator and the memory are positive and the result is
-        // negative, an overflow happened.
-        // * If the accumulator and the memory are negative and the result is
-        // positive, an overflow happened.
-        // * Otherwise, no overflow happened.
-        let acc_pos = self.a & 0x80 == 0;
-        let mem_pos = self.data.fetched & 0x80 == 0;
-        let res_pos = addition & 0x80 == 0;
-        let overflow = (acc_pos && mem_pos && !res_pos) || (!acc_pos && !mem_pos && res_pos);
-        self.status.set(CpuFlags::V, overflow);
-
-        // Set the accumulator to the 8-bit result of the addition
-        self.a = (addition & 0x00FF) as u8;
+        self.add_with_carry(self.data.fetched);
         1
     }
 
@@ -584,24 +589,12 @@ impl Cpu {
     ///
     /// No operation is executed.
     ///
-    /// The following link presents the CPU illegal opcodes sometimes
-    /// used by NES games:
-    /// <https://wiki.nesdev.com/w/index.php/CPU_unofficial_opcodes>
-    ///
-    /// Some of the illegal opcodes represent NOP instructions. They
-    /// may require additional cycles.
+    /// Most of the unofficial NOPs have an operand, which is read
+    /// from memory even though it is ignored. The ones using absolute
+    /// addressing with X offset may need an additional clock cycle.
     pub fn nop(&mut self) -> u8 {
-        match self.data.opcode {
-            // may need additional cycles
-            0x1C => 1,
-            0x3C => 1,
-            0x5C => 1,
-            0x7C => 1,
-            0xDC => 1,
-            0xFC => 1,
-            // do not need additional cycles
-            _ => 0,
-        }
+        self.fetch();
+        1
     }
 
     /// OR Memory with Accumulator
@@ -673,6 +666,7 @@ impl Cpu {
         self.stkp += 1;
         let status = self.read(STACK_BASE + self.stkp as u16);
         self.status = CpuFlags::from_bits_truncate(status);
+        self.status.set(CpuFlags::B, false);
         self.status.set(CpuFlags::U, true);
         0
     }
@@ -782,42 +776,7 @@ impl Cpu {
         // (this way, the implementation is similar to Cpu::adc)
 
         self.fetch();
-
-        // Changes the value of self.fetched to reflect the discussion above
-        let fetched_not = !self.data.fetched;
-
-        // 0 or 1
-        let c = u8::from(self.status.contains(CpuFlags::C));
-
-        // Can't overflow due to the same reasons found in `Cpu::adc`
-        let addition = self.a as u16 + fetched_not as u16 + c as u16;
-
-        // If the result is over 0xFF, a carry bit is needed
-        self.status.set(CpuFlags::C, addition > 0xFF);
-
-        // If the 8 bit addition results in 0x00, the Z flag is set to 1
-        // We need to remove the higher 8 bits of the 16-bit addition
-        self.status.set(CpuFlags::Z, addition & 0x00FF == 0);
-
-        // If the most significant bit of the 8-bit addition is 1,
-        // the result may be negative if it is treated like so. The N
-        // flag will be set to 1
-        self.status.set(CpuFlags::N, addition & 0x0080 != 0);
-
-        // * If the accumulator and the memory are positive and the result is
-        // negative, an overflow happened.
-        // * If the accumulator and the memory are negative and the result is
-        // positive, an overflow happened.
-        // * Otherwise, no overflow happened.
-        let acc_pos = self.a & 0x80 == 0;
-        let mem_pos = fetched_not & 0x80 == 0;
-        let res_pos = addition & 0x80 == 0;
-        let overflow = (acc_pos && mem_pos && !res_pos) || (!acc_pos && !mem_pos && res_pos);
-        self.status.set(CpuFlags::V, overflow);
-
-        // Set the accumulator to the 8-bit result of the addition
-        self.a = (addition & 0x00FF) as u8;
-
+        self.add_with_carry(!self.data.fetched);
         1
     }
 
@@ -945,8 +904,352 @@ impl Cpu {
         0
     }
 
-    /// Captures illegal opcodes
-    pub fn xxx(&mut self) -> u8 {
+    // Unofficial opcodes
+    //
+    // The following instructions are not part of the 6502's standard,
+    // but are consequences of how its instruction decoder works.
+    // Some games rely on them. More information can be found at
+    // <https://www.nesdev.org/wiki/CPU_unofficial_opcodes>
+
+    /// Helper function. Stores `value & (H + 1)`, where H is the high byte
+    /// of the base address, before `index` was added to it.
+    ///
+    /// Used by the unstable SHA, SHX, SHY and TAS instructions. If adding
+    /// the index crosses a page, the high byte of the address is replaced
+    /// by the stored value.
+    fn store_high_and(&mut self, value: u8, index: u8) {
+        let base = self.data.addr_abs.wrapping_sub(index as u16);
+        let high = (base >> 8) as u8;
+        let value = value & high.wrapping_add(1);
+
+        let mut addr = self.data.addr_abs;
+        if base & 0xFF00 != addr & 0xFF00 {
+            addr = ((value as u16) << 8) | (addr & 0x00FF);
+        }
+        self.write(addr, value);
+    }
+
+    /// AND Immediate then Logical Shift Right (unofficial)
+    ///
+    /// A := (A & M) >> 1
+    ///
+    /// May change the N, Z, C flags.
+    pub fn alr(&mut self) -> u8 {
+        self.fetch();
+
+        let value = self.a & self.data.fetched;
+        self.status.set(CpuFlags::C, value & 0x01 != 0);
+        self.a = value >> 1;
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
+
+        0
+    }
+
+    /// AND Immediate then copy N to C (unofficial)
+    ///
+    /// A := A & M,
+    /// C := N
+    ///
+    /// May change the N, Z, C flags.
+    pub fn anc(&mut self) -> u8 {
+        self.fetch();
+
+        self.a &= self.data.fetched;
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
+        self.status.set(CpuFlags::C, self.a & 0x80 != 0);
+
+        0
+    }
+
+    /// AND X then AND Immediate (unofficial, unstable)
+    ///
+    /// A := (A | MAGIC) & X & M
+    ///
+    /// The magic constant depends on the chip and its temperature.
+    /// We use 0xEE, like most emulators.
+    ///
+    /// May change the N, Z flags.
+    pub fn ane(&mut self) -> u8 {
+        self.fetch();
+
+        self.a = (self.a | 0xEE) & self.x & self.data.fetched;
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
+
+        0
+    }
+
+    /// AND Immediate then Rotate Right (unofficial)
+    ///
+    /// A := (A & M) rotated right
+    ///
+    /// Sets C to bit 6 of the result and V to bit 6 XOR bit 5.
+    ///
+    /// May change the N, Z, C, V flags.
+    pub fn arr(&mut self) -> u8 {
+        self.fetch();
+
+        let carry = u8::from(self.status.contains(CpuFlags::C)) << 7;
+        self.a = carry | ((self.a & self.data.fetched) >> 1);
+
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
+        self.status.set(CpuFlags::C, self.a & 0x40 != 0);
+        self.status
+            .set(CpuFlags::V, ((self.a >> 6) ^ (self.a >> 5)) & 0x01 != 0);
+
+        0
+    }
+
+    /// AND X with Accumulator then Subtract Immediate (unofficial)
+    ///
+    /// X := (A & X) - M
+    ///
+    /// The carry is set like in [`Cpu::cmp`], and the subtraction
+    /// ignores the previous carry.
+    ///
+    /// May change the N, Z, C flags.
+    pub fn axs(&mut self) -> u8 {
+        self.fetch();
+
+        let value = self.a & self.x;
+        self.status.set(CpuFlags::C, value >= self.data.fetched);
+        self.x = value.wrapping_sub(self.data.fetched);
+        self.set_negative_flag(self.x);
+        self.set_zero_flag(self.x);
+
+        0
+    }
+
+    /// Decrement Memory then Compare (unofficial)
+    ///
+    /// M := M - 1,
+    /// compares A with M like [`Cpu::cmp`].
+    ///
+    /// May change the N, Z, C flags.
+    pub fn dcp(&mut self) -> u8 {
+        self.fetch();
+
+        let new = self.data.fetched.wrapping_sub(1);
+        self.write(self.data.addr_abs, new);
+
+        let result = self.a.wrapping_sub(new);
+        self.status.set(CpuFlags::C, self.a >= new);
+        self.set_zero_flag(result);
+        self.set_negative_flag(result);
+
+        0
+    }
+
+    /// Increment Memory then Subtract with Carry (unofficial)
+    ///
+    /// M := M + 1,
+    /// A := A - M - (1-C)
+    ///
+    /// May change the N, Z, C, V flags.
+    pub fn isc(&mut self) -> u8 {
+        self.fetch();
+
+        let new = self.data.fetched.wrapping_add(1);
+        self.write(self.data.addr_abs, new);
+        self.add_with_carry(!new);
+
+        0
+    }
+
+    /// Halt the CPU (unofficial)
+    ///
+    /// Also known as KIL. The CPU stops executing instructions and
+    /// ignores interrupts until it is reset.
+    pub fn jam(&mut self) -> u8 {
+        self.jammed = true;
+        // keep pointing at the instruction that halted the CPU
+        self.pc = self.pc.wrapping_sub(1);
+        0
+    }
+
+    /// AND Memory with Stack Pointer (unofficial)
+    ///
+    /// A, X, STKP := M & STKP
+    ///
+    /// May change the N, Z flags.
+    ///
+    /// May need an additional clock cycle.
+    pub fn las(&mut self) -> u8 {
+        self.fetch();
+
+        let value = self.data.fetched & self.stkp;
+        self.a = value;
+        self.x = value;
+        self.stkp = value;
+        self.set_negative_flag(value);
+        self.set_zero_flag(value);
+
+        1
+    }
+
+    /// Load Accumulator and Index X with Memory (unofficial)
+    ///
+    /// A, X := M
+    ///
+    /// May change the N, Z flags.
+    ///
+    /// May need an additional clock cycle.
+    pub fn lax(&mut self) -> u8 {
+        self.fetch();
+
+        self.a = self.data.fetched;
+        self.x = self.data.fetched;
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
+
+        1
+    }
+
+    /// Load Accumulator and Index X with Immediate (unofficial, unstable)
+    ///
+    /// A, X := (A | MAGIC) & M
+    ///
+    /// Same as [`Cpu::ane`], the magic constant depends on the chip.
+    /// The NES' CPU behaves as if it was 0xFF. Named `LAX #imm` in the
+    /// lookup table, like ca65 does.
+    ///
+    /// May change the N, Z flags.
+    pub fn lxa(&mut self) -> u8 {
+        self.fetch();
+
+        self.a = self.data.fetched;
+        self.x = self.data.fetched;
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
+
+        0
+    }
+
+    /// Rotate Left then AND with Accumulator (unofficial)
+    ///
+    /// M := M rotated left,
+    /// A := A & M
+    ///
+    /// May change the N, Z, C flags.
+    pub fn rla(&mut self) -> u8 {
+        self.fetch();
+
+        let carry = u8::from(self.status.contains(CpuFlags::C));
+        let new = (self.data.fetched << 1) | carry;
+        self.status.set(CpuFlags::C, self.data.fetched & 0x80 != 0);
+        self.write(self.data.addr_abs, new);
+
+        self.a &= new;
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
+
+        0
+    }
+
+    /// Rotate Right then Add with Carry (unofficial)
+    ///
+    /// M := M rotated right,
+    /// A := A + M + C
+    ///
+    /// The carry used by the addition is the bit rotated out of memory.
+    ///
+    /// May change the N, Z, C, V flags.
+    pub fn rra(&mut self) -> u8 {
+        self.fetch();
+
+        let carry = u8::from(self.status.contains(CpuFlags::C)) << 7;
+        let new = carry | (self.data.fetched >> 1);
+        self.status.set(CpuFlags::C, self.data.fetched & 0x01 != 0);
+        self.write(self.data.addr_abs, new);
+        self.add_with_carry(new);
+
+        0
+    }
+
+    /// Store Accumulator AND Index X (unofficial)
+    ///
+    /// M := A & X
+    pub fn sax(&mut self) -> u8 {
+        self.write(self.data.addr_abs, self.a & self.x);
+        0
+    }
+
+    /// Store Accumulator AND Index X AND High Byte (unofficial, unstable)
+    ///
+    /// M := A & X & (H + 1)
+    ///
+    /// Also known as AHX.
+    pub fn sha(&mut self) -> u8 {
+        self.store_high_and(self.a & self.x, self.y);
+        0
+    }
+
+    /// Store Index X AND High Byte (unofficial, unstable)
+    ///
+    /// M := X & (H + 1)
+    pub fn shx(&mut self) -> u8 {
+        self.store_high_and(self.x, self.y);
+        0
+    }
+
+    /// Store Index Y AND High Byte (unofficial, unstable)
+    ///
+    /// M := Y & (H + 1)
+    pub fn shy(&mut self) -> u8 {
+        self.store_high_and(self.y, self.x);
+        0
+    }
+
+    /// Shift Left then OR with Accumulator (unofficial)
+    ///
+    /// M := M << 1,
+    /// A := A | M
+    ///
+    /// May change the N, Z, C flags.
+    pub fn slo(&mut self) -> u8 {
+        self.fetch();
+
+        let new = self.data.fetched << 1;
+        self.status.set(CpuFlags::C, self.data.fetched & 0x80 != 0);
+        self.write(self.data.addr_abs, new);
+
+        self.a |= new;
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
+
+        0
+    }
+
+    /// Shift Right then Exclusive-OR with Accumulator (unofficial)
+    ///
+    /// M := M >> 1,
+    /// A := A XOR M
+    ///
+    /// May change the N, Z, C flags.
+    pub fn sre(&mut self) -> u8 {
+        self.fetch();
+
+        let new = self.data.fetched >> 1;
+        self.status.set(CpuFlags::C, self.data.fetched & 0x01 != 0);
+        self.write(self.data.addr_abs, new);
+
+        self.a ^= new;
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
+
+        0
+    }
+
+    /// Transfer Accumulator AND Index X to Stack Pointer (unofficial, unstable)
+    ///
+    /// STKP := A & X,
+    /// M := STKP & (H + 1)
+    pub fn tas(&mut self) -> u8 {
+        self.stkp = self.a & self.x;
+        self.store_high_and(self.stkp, self.y);
         0
     }
 }
@@ -13,7 +13,29 @@ pub struct Instruction {
     pub execute: fn(cpu: &mut Cpu) -> u8,
 }
 
+/// Mnemonics of the opcodes that are not part of the 6502's standard,
+/// besides the NOPs and SBC.
+const UNOFFICIAL_MNEMONICS: [&str; 19] = [
+    "ALR", "ANC", "ANE", "ARR", "AXS", "DCP", "ISC", "JAM", "LAS", "LAX", "RLA", "RRA", "SAX",
+    "SHA", "SHX", "SHY", "SLO", "SRE", "TAS",
+];
+
 impl Instruction {
+    /// Name of the instruction, e.g. "LDA".
+    pub fn mnemonic(&self) -> &'static str {
+        // names are of the form "XA9_LDA"
+        &self._name[4..]
+    }
+
+    /// Returns true if the instruction is part of the 6502's standard.
+    pub fn official(&self) -> bool {
+        match self._opcode {
+            0xEA => true,
+            0xEB => false,
+            _ => self.mnemonic() != "NOP" && !UNOFFICIAL_MNEMONICS.contains(&self.mnemonic()),
+        }
+    }
+
     pub fn lookup(opcode: u8) -> &'static Instruction {
         // It is impossible for the `opcode` index to be out
         // of bounds, since its value ranges from 0 to 255
@@ -27,11 +27,14 @@
 //! * Address mode
 //! * Cycles
 
-mod addressing;
+pub(crate) mod addressing;
 mod flags;
-mod instructions;
+pub(crate) mod instructions;
 
+use crate::disasm;
+use crate::state::{StateError, StateReader, StateWriter};
 use crate::system::bus::Bus;
+use crate::trace::CpuTrace;
 use flags::CpuFlags;
 use instructions::Instruction;
 
@@ -56,6 +59,10 @@ pub struct Cpu {
     /// STATUS register
     pub(crate) status: CpuFlags,
 
+    /// Set by the JAM instructions, which halt the CPU until
+    /// it is reset
+    jammed: bool,
+
     data: CpuData,
 }
 
@@ -101,6 +108,8 @@ impl Cpu {
             pc: 0,
             status: CpuFlags::empty(),
 
+            jammed: false,
+
             data: CpuData {
                 cycles: 0,
                 opcode: 0,
@@ -140,13 +149,13 @@ impl Cpu {
 
     /// **Resets the CPU into a known state**
     ///
-    /// Takes 8 CPU cycles.
+    /// Takes 7 CPU cycles.
     ///
     /// A = 0,
     /// X = 0,
     /// Y = 0,
     /// STKP = 0xFD,
-    /// STATUS = 0x00 | CpuFlags::U
+    /// STATUS = CpuFlags::I | CpuFlags::U
     ///
     ///
     /// The PC will be set to the value pointed by the
@@ -161,18 +170,20 @@ impl Cpu {
         self.x = 0;
         self.y = 0;
         self.stkp = 0xFD;
-        self.status = CpuFlags::empty() | CpuFlags::U;
+        self.status = CpuFlags::I | CpuFlags::U;
+        self.jammed = false;
 
         self.data.addr_abs = 0;
         self.data.addr_rel = 0;
         self.data.fetched = 0;
 
-        self.data.cycles = 8;
+        self.data.cycles = 7;
     }
 
     /// **Interrupt request**
     ///
-    /// Only executes if the I flag is 0.
+    /// Only executes if the I flag is 0 and the CPU is not jammed.
+    /// Returns true if the interrupt was taken.
     ///
     /// Takes 7 cycles.
     ///
@@ -185,10 +196,9 @@ impl Cpu {
     ///
     /// The PC will be set to the value pointed by the
     /// 16-bit pointer found at 0xFFFE
-    #[allow(dead_code)]
-    pub fn irq(&mut self) {
-        if self.status.contains(CpuFlags::I) {
-            return;
+    pub fn irq(&mut self) -> bool {
+        if self.status.contains(CpuFlags::I) || self.jammed {
+            return false;
         }
 
         self.write(
@@ -211,6 +221,7 @@ impl Cpu {
         self.pc = (high << 8) | low;
 
         self.data.cycles = 7;
+        true
     }
 
     /// **Non-maskable interrupt**
@@ -219,10 +230,16 @@ impl Cpu {
     ///
     /// Same as the _interrupt request_ (IRQ), but it doesn't check the I flag
     /// before executing.
+    /// Ignored while the CPU is jammed. Returns true if the interrupt
+    /// was taken.
     ///
     /// The PC will be set to the value pointed by the
     /// 16-bit pointer found at 0xFFFA
-    pub fn nmi(&mut self) {
+    pub fn nmi(&mut self) -> bool {
+        if self.jammed {
+            return false;
+        }
+
         self.write(
             STACK_BASE + self.stkp as u16,
             ((self.pc >> 8) & 0x00FF) as u8,
@@ -243,14 +260,19 @@ impl Cpu {
         self.pc = (high << 8) | low;
 
         self.data.cycles = 8;
+        true
     }
 
     /// **Executes a clock cycle**
     ///
     /// If an instruction has clock cycles pending, does nothing.
     /// Otherwise, it reads the current instruction from the PC
-    /// and executes it.
+    /// and executes it. Does nothing while the CPU is jammed.
     pub fn clock(&mut self) {
+        if self.jammed {
+            return;
+        }
+
         if self.data.cycles != 0 {
             self.data.cycles -= 1;
             return;
@@ -266,18 +288,17 @@ impl Cpu {
         self.status.set(CpuFlags::U, true);
 
         // Each instructions needs a different amount of
-        // clock cycles.
-        let mut cycles = ins.cycles;
+        // clock cycles. Set before the instruction executes, since
+        // branches add their own cycles.
+        self.data.cycles = ins.cycles;
 
         // Call instruction
         let add_cycle1 = (ins.addrmode.run)(self);
         let add_cycle2 = (ins.execute)(self);
 
         // `addrmode` and `execute` return either 0 or 1.
-        // If both return 0, an additional cycle is needed.
-        cycles += add_cycle1 & add_cycle2;
-
-        self.data.cycles = cycles;
+        // If both return 1, an additional cycle is needed.
+        self.data.cycles += add_cycle1 & add_cycle2;
 
         // Must be always set to true
         self.status.set(CpuFlags::U, true);
@@ -285,6 +306,79 @@ impl Cpu {
         self.data.cycles -= 1;
     }
 
+    /// Returns true if the current instruction has finished executing,
+    /// which means the next clock cycle will start a new instruction.
+    pub fn complete(&self) -> bool {
+        self.data.cycles == 0
+    }
+
+    /// State of the CPU before the next instruction is executed,
+    /// read without side effects. Must only be called when
+    /// `complete` returns true.
+    pub fn trace(&self, cycle: u64) -> CpuTrace {
+        let mut bytes: [u8; 3] =
+            std::array::from_fn(|i| self.bus.peek(self.pc.wrapping_add(i as u16)));
+        // decoded like the disassembler does, three bytes always hold
+        // a whole instruction
+        let len = disasm::decode(&bytes, self.pc).map_or(1, |instruction| instruction.len);
+        bytes[len as usize..].fill(0);
+
+        CpuTrace {
+            pc: self.pc,
+            bytes,
+            len,
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            p: self.status.bits(),
+            sp: self.stkp,
+            cycle,
+            scanline: self.bus.ppu.scanline(),
+            dot: self.bus.ppu.dot(),
+        }
+    }
+
+    /// Returns true if the CPU was halted by a JAM instruction.
+    /// Only a reset brings it back.
+    pub fn jammed(&self) -> bool {
+        self.jammed
+    }
+
+    /// Writes the registers and the progress of the current
+    /// instruction to a save state. The bus is saved separately.
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.a);
+        state.write_u8(self.x);
+        state.write_u8(self.y);
+        state.write_u8(self.stkp);
+        state.write_u16(self.pc);
+        state.write_u8(self.status.bits());
+        state.write_bool(self.jammed);
+
+        state.write_u8(self.data.cycles);
+        state.write_u8(self.data.opcode);
+        state.write_u8(self.data.fetched);
+        state.write_u16(self.data.addr_abs);
+        state.write_u16(self.data.addr_rel);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.a = state.read_u8()?;
+        self.x = state.read_u8()?;
+        self.y = state.read_u8()?;
+        self.stkp = state.read_u8()?;
+        self.pc = state.read_u16()?;
+        self.status = CpuFlags::from_bits_truncate(state.read_u8()?);
+        self.jammed = state.read_bool()?;
+
+        self.data.cycles = state.read_u8()?;
+        self.data.opcode = state.read_u8()?;
+        self.data.fetched = state.read_u8()?;
+        self.data.addr_abs = state.read_u16()?;
+        self.data.addr_rel = state.read_u16()?;
+        Ok(())
+    }
+
     /// Fetches the data required by the current instruction.
     ///
     /// Not used by the implied address mode.
@@ -303,3 +397,50 @@ impl Default for Cpu {
         Self::new()
     }
 }
+
+#[test]
+fn test_unofficial_opcodes() {
+    use crate::cartridge::Cartridge;
+
+    let mut cpu = Cpu::new();
+    cpu.bus
+        .insert_cartridge(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    cpu.reset();
+
+    let program = [
+        0xA7, 0x10, // LAX $10
+        0xC7, 0x11, // DCP $11
+        0x87, 0x12, // SAX $12
+        0x1C, 0xFF, 0x01, // NOP $01FF,X (page crossed)
+        0x02, // JAM
+    ];
+    for (i, &byte) in program.iter().enumerate() {
+        cpu.write(0x0200 + i as u16, byte);
+    }
+    cpu.write(0x0010, 0x85);
+    cpu.write(0x0011, 0x86);
+    cpu.pc = 0x0200;
+    cpu.x = 0x01;
+    cpu.data.cycles = 0;
+
+    let mut cycles = 0;
+    while !cpu.jammed() {
+        cpu.clock();
+        cycles += 1;
+    }
+
+    assert_eq!((cpu.a, cpu.x), (0x85, 0x85));
+    assert_eq!(cpu.read(0x0011), 0x85);
+    assert!(cpu.status.contains(CpuFlags::Z | CpuFlags::C));
+    assert_eq!(cpu.read(0x0012), 0x85);
+    // 3 + 5 + 3 + 5 cycles, then the JAM opcode is fetched
+    assert_eq!(cycles, 17);
+
+    // a jammed CPU stays at the JAM opcode and ignores interrupts
+    cpu.nmi();
+    cpu.clock();
+    assert_eq!(cpu.pc, 0x0209);
+
+    cpu.reset();
+    assert!(!cpu.jammed());
+}
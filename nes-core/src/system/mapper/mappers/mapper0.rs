@@ -1,63 +1,50 @@
-use crate::system::mapper::Mapper;
+use crate::system::mapper::{Mapped, Mapper};
 use crate::system::ram::RAM_ADDR_END;
 
-#[derive(Debug)]
+/// NROM. Has no registers, the cartridge's memory is mapped directly.
+#[derive(Debug, Clone)]
 pub struct Mapper0 {
     program_banks: u8,
-    character_banks: u8,
 }
 
 impl Mapper0 {
-    pub fn new(program_banks: u8, character_banks: u8) -> Self {
-        Mapper0 {
-            program_banks,
-            character_banks,
-        }
+    pub fn new(program_banks: u8, _character_banks: u8) -> Self {
+        Mapper0 { program_banks }
     }
-}
 
-impl Mapper for Mapper0 {
-    fn cpu_map_read(&self, addr: u16) -> Option<u32> {
-        let mirror = if self.program_banks > 1 {
+    fn program_mirror(&self) -> u16 {
+        if self.program_banks > 1 {
             // 32 KB ROM
             0x7FFF
         } else {
             // 16 KB
             0x3FFF
-        };
-
-        match addr {
-            0x8000..=0xFFFF => Some(addr as u32 & mirror),
-            _ => None,
         }
     }
+}
 
-    fn cpu_map_write(&self, addr: u16, _data: u8) -> Option<u32> {
-        let mirror = if self.program_banks > 1 {
-            // 32 KB ROM
-            0x7FFF
-        } else {
-            // 16 KB
-            0x3FFF
-        };
-
+impl Mapper for Mapper0 {
+    fn cpu_map_read(&self, addr: u16) -> Mapped {
         match addr {
-            0x8000..=0xFFFF => Some(addr as u32 & mirror),
-            _ => None,
+            0x6000..=0x7FFF => Mapped::Ram((addr & 0x1FFF) as u32),
+            0x8000..=0xFFFF => Mapped::Memory((addr & self.program_mirror()) as u32),
+            _ => Mapped::Unmapped,
         }
     }
 
-    fn ppu_map_read(&self, addr: u16) -> Option<u32> {
+    fn cpu_map_write(&mut self, addr: u16, _data: u8) -> Mapped {
+        // writes to ROM are ignored by the cartridge
         match addr {
-            0x0000..=RAM_ADDR_END => Some(addr as u32),
-            _ => None,
+            0x6000..=0x7FFF => Mapped::Ram((addr & 0x1FFF) as u32),
+            0x8000..=0xFFFF => Mapped::Memory((addr & self.program_mirror()) as u32),
+            _ => Mapped::Unmapped,
         }
     }
 
-    fn ppu_map_write(&self, addr: u16) -> Option<u32> {
+    fn ppu_map_read(&self, addr: u16) -> Mapped {
         match addr {
-            (0x0000..=RAM_ADDR_END) if self.character_banks == 0 => Some(addr as u32),
-            _ => None,
+            0x0000..=RAM_ADDR_END => Mapped::Memory(addr as u32),
+            _ => Mapped::Unmapped,
         }
     }
 }
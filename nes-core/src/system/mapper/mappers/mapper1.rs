@@ -0,0 +1,222 @@
+use crate::cartridge::CartridgeMirror;
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::mapper::{Mapped, Mapper};
+
+/// MMC1 (SxROM).
+///
+/// The registers are written serially: each write to $8000-$FFFF shifts
+/// bit 0 of the data into a shift register. On the fifth write, the
+/// shift register's value is copied into the register selected by the
+/// address of that write. Writing a value with bit 7 set resets the
+/// shift register.
+///
+/// Registers:
+/// * $8000-$9FFF: control (mirroring, PRG and CHR bank modes)
+/// * $A000-$BFFF: CHR bank 0
+/// * $C000-$DFFF: CHR bank 1
+/// * $E000-$FFFF: PRG bank and PRG-RAM enable
+#[derive(Debug, Clone)]
+pub struct Mapper1 {
+    program_banks: u8,
+    character_banks: u8,
+
+    shift: u8,
+    shift_count: u8,
+
+    control: u8,
+    character_bank0: u8,
+    character_bank1: u8,
+    program_bank: u8,
+}
+
+impl Mapper1 {
+    pub fn new(program_banks: u8, character_banks: u8) -> Self {
+        let mut mapper = Mapper1 {
+            program_banks,
+            character_banks,
+            shift: 0,
+            shift_count: 0,
+            control: 0,
+            character_bank0: 0,
+            character_bank1: 0,
+            program_bank: 0,
+        };
+        mapper.reset();
+        mapper
+    }
+
+    fn program_ram_enabled(&self) -> bool {
+        self.program_bank & 0x10 == 0
+    }
+
+    /// Writes the value of the shift register to the register
+    /// selected by `addr`.
+    fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x8000..=0x9FFF => self.control = data,
+            0xA000..=0xBFFF => self.character_bank0 = data,
+            0xC000..=0xDFFF => self.character_bank1 = data,
+            _ => self.program_bank = data,
+        }
+    }
+
+    /// Offset of `addr` into the program ROM, according to the
+    /// selected PRG bank mode.
+    fn program_offset(&self, addr: u16) -> u32 {
+        let bank = (self.program_bank & 0x0F) as u32;
+        let last_bank = self.program_banks.saturating_sub(1) as u32;
+
+        let bank_16k = match (self.control >> 2) & 0x03 {
+            // switch 32 KB at $8000, ignoring the low bit of the bank number
+            0 | 1 => {
+                let bank_32k = bank & 0x0E;
+                return (bank_32k * 0x4000 + (addr & 0x7FFF) as u32) % self.program_size();
+            }
+            // fix the first bank at $8000, switch 16 KB bank at $C000
+            2 => {
+                if addr < 0xC000 {
+                    0
+                } else {
+                    bank
+                }
+            }
+            // fix the last bank at $C000, switch 16 KB bank at $8000
+            _ => {
+                if addr < 0xC000 {
+                    bank
+                } else {
+                    last_bank
+                }
+            }
+        };
+
+        (bank_16k * 0x4000 + (addr & 0x3FFF) as u32) % self.program_size()
+    }
+
+    /// Offset of `addr` into the character memory, according to the
+    /// selected CHR bank mode.
+    fn character_offset(&self, addr: u16) -> u32 {
+        let offset = if self.control & 0x10 == 0 {
+            // switch 8 KB at a time, ignoring the low bit of the bank number
+            (self.character_bank0 & 0x1E) as u32 * 0x1000 + (addr & 0x1FFF) as u32
+        } else {
+            // switch two separate 4 KB banks
+            let bank = if addr < 0x1000 {
+                self.character_bank0
+            } else {
+                self.character_bank1
+            };
+            bank as u32 * 0x1000 + (addr & 0x0FFF) as u32
+        };
+
+        offset % self.character_size()
+    }
+
+    fn program_size(&self) -> u32 {
+        (self.program_banks.max(1) as u32) * 0x4000
+    }
+
+    fn character_size(&self) -> u32 {
+        (self.character_banks.max(1) as u32) * 0x2000
+    }
+}
+
+impl Mapper for Mapper1 {
+    fn cpu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.program_ram_enabled() => Mapped::Ram((addr & 0x1FFF) as u32),
+            0x8000..=0xFFFF => Mapped::Memory(self.program_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.program_ram_enabled() => Mapped::Ram((addr & 0x1FFF) as u32),
+            0x8000..=0xFFFF => {
+                if data & 0x80 != 0 {
+                    // reset the shift register and fix the last PRG bank at $C000
+                    self.shift = 0;
+                    self.shift_count = 0;
+                    self.control |= 0x0C;
+                    return Mapped::Register;
+                }
+
+                self.shift |= (data & 0x01) << self.shift_count;
+                self.shift_count += 1;
+
+                if self.shift_count == 5 {
+                    self.write_register(addr, self.shift);
+                    self.shift = 0;
+                    self.shift_count = 0;
+                }
+
+                Mapped::Register
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn ppu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x0000..=0x1FFF => Mapped::Memory(self.character_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn mirror(&self) -> Option<CartridgeMirror> {
+        Some(match self.control & 0x03 {
+            0 => CartridgeMirror::OneScreenLow,
+            1 => CartridgeMirror::OneScreenHigh,
+            2 => CartridgeMirror::Vertical,
+            _ => CartridgeMirror::Horizontal,
+        })
+    }
+
+    fn reset(&mut self) {
+        self.shift = 0;
+        self.shift_count = 0;
+        // on power-up, the last PRG bank is fixed at $C000
+        self.control = 0x0C;
+        self.character_bank0 = 0;
+        self.character_bank1 = 0;
+        self.program_bank = 0;
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.shift);
+        state.write_u8(self.shift_count);
+        state.write_u8(self.control);
+        state.write_u8(self.character_bank0);
+        state.write_u8(self.character_bank1);
+        state.write_u8(self.program_bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.shift = state.read_u8()?;
+        self.shift_count = state.read_u8()?;
+        self.control = state.read_u8()?;
+        self.character_bank0 = state.read_u8()?;
+        self.character_bank1 = state.read_u8()?;
+        self.program_bank = state.read_u8()?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_serial_write() {
+    let mut mapper = Mapper1::new(8, 0);
+
+    // writes 0b00010 to the control register, one bit at a time
+    for bit in [0, 1, 0, 0, 0] {
+        assert_eq!(mapper.cpu_map_write(0x8000, bit), Mapped::Register);
+    }
+    assert_eq!(mapper.mirror(), Some(CartridgeMirror::Vertical));
+
+    // PRG mode 0 switches 32 KB at a time
+    for bit in [1, 1, 0, 0, 0] {
+        mapper.cpu_map_write(0xE000, bit);
+    }
+    assert_eq!(mapper.cpu_map_read(0x8000), Mapped::Memory(2 * 0x4000));
+    assert_eq!(mapper.cpu_map_read(0xC000), Mapped::Memory(3 * 0x4000));
+}
@@ -0,0 +1,73 @@
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::mapper::{Mapped, Mapper};
+
+/// UxROM.
+///
+/// Writes to $8000-$FFFF select the 16 KB PRG bank at $8000. The last
+/// bank is fixed at $C000. Usually has CHR-RAM. Has no PRG-RAM,
+/// $6000-$7FFF is open bus unless a NES 2.0 header asks for some.
+#[derive(Debug, Clone)]
+pub struct Mapper2 {
+    program_banks: u8,
+    program_ram: bool,
+
+    program_bank: u8,
+}
+
+impl Mapper2 {
+    pub fn new(program_banks: u8, _character_banks: u8, program_ram: bool) -> Self {
+        Mapper2 {
+            program_banks,
+            program_ram,
+            program_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Mapper2 {
+    fn cpu_map_read(&self, addr: u16) -> Mapped {
+        let bank = match addr {
+            0x6000..=0x7FFF if self.program_ram => return Mapped::Ram((addr & 0x1FFF) as u32),
+            0x8000..=0xBFFF => self.program_bank % self.program_banks.max(1),
+            0xC000..=0xFFFF => self.program_banks.saturating_sub(1),
+            _ => return Mapped::Unmapped,
+        };
+
+        Mapped::Memory(bank as u32 * 0x4000 + (addr & 0x3FFF) as u32)
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.program_ram => Mapped::Ram((addr & 0x1FFF) as u32),
+            0x8000..=0xFFFF => {
+                self.program_bank = data;
+                Mapped::Register
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn ppu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x0000..=0x1FFF => Mapped::Memory(addr as u32),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.program_bank = 0;
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        true
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.program_bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.program_bank = state.read_u8()?;
+        Ok(())
+    }
+}
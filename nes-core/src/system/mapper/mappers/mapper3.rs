@@ -0,0 +1,85 @@
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::mapper::{Mapped, Mapper};
+
+/// CNROM.
+///
+/// Writes to $8000-$FFFF select the 8 KB CHR bank. PRG-ROM is mapped
+/// the same way as NROM. Has no PRG-RAM, $6000-$7FFF is open bus
+/// unless a NES 2.0 header asks for some.
+#[derive(Debug, Clone)]
+pub struct Mapper3 {
+    program_banks: u8,
+    character_banks: u8,
+    program_ram: bool,
+
+    character_bank: u8,
+}
+
+impl Mapper3 {
+    pub fn new(program_banks: u8, character_banks: u8, program_ram: bool) -> Self {
+        Mapper3 {
+            program_banks,
+            character_banks,
+            program_ram,
+            character_bank: 0,
+        }
+    }
+
+    fn program_mirror(&self) -> u16 {
+        if self.program_banks > 1 {
+            // 32 KB ROM
+            0x7FFF
+        } else {
+            // 16 KB
+            0x3FFF
+        }
+    }
+}
+
+impl Mapper for Mapper3 {
+    fn cpu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.program_ram => Mapped::Ram((addr & 0x1FFF) as u32),
+            0x8000..=0xFFFF => Mapped::Memory((addr & self.program_mirror()) as u32),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.program_ram => Mapped::Ram((addr & 0x1FFF) as u32),
+            0x8000..=0xFFFF => {
+                self.character_bank = data;
+                Mapped::Register
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn ppu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x0000..=0x1FFF => {
+                let bank = self.character_bank % self.character_banks.max(1);
+                Mapped::Memory(bank as u32 * 0x2000 + addr as u32)
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.character_bank = 0;
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        true
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.character_bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.character_bank = state.read_u8()?;
+        Ok(())
+    }
+}
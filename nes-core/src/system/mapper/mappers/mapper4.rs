@@ -0,0 +1,287 @@
+use crate::cartridge::CartridgeMirror;
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::mapper::{Mapped, Mapper};
+
+/// MMC3 (TxROM).
+///
+/// Switches 8 KB PRG banks and 1 KB or 2 KB CHR banks through eight bank
+/// registers, and contains a scanline counter that can send interrupt
+/// requests to the CPU.
+///
+/// Registers, selected by the address range and whether it is even or odd:
+/// * $8000-$9FFF: bank select (even), bank data (odd)
+/// * $A000-$BFFF: mirroring (even), PRG-RAM protect (odd)
+/// * $C000-$DFFF: IRQ latch (even), IRQ reload (odd)
+/// * $E000-$FFFF: IRQ disable (even), IRQ enable (odd)
+#[derive(Debug, Clone)]
+pub struct Mapper4 {
+    program_banks: u8,
+    character_banks: u8,
+
+    /// Index of the bank register updated by the next bank data write
+    target_register: u8,
+    /// Swaps the PRG banks at $8000 and $C000
+    program_mode: bool,
+    /// Swaps the CHR banks at $0000 and $1000
+    character_inversion: bool,
+    registers: [u8; 8],
+
+    mirror: CartridgeMirror,
+    program_ram_enabled: bool,
+    program_ram_write_protected: bool,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+}
+
+impl Mapper4 {
+    pub fn new(program_banks: u8, character_banks: u8) -> Self {
+        let mut mapper = Mapper4 {
+            program_banks,
+            character_banks,
+            target_register: 0,
+            program_mode: false,
+            character_inversion: false,
+            registers: [0; 8],
+            mirror: CartridgeMirror::Vertical,
+            program_ram_enabled: true,
+            program_ram_write_protected: false,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+        };
+        mapper.reset();
+        mapper
+    }
+
+    /// Amount of 8 KB PRG banks.
+    fn program_banks_8k(&self) -> u32 {
+        self.program_banks.max(1) as u32 * 2
+    }
+
+    /// Amount of 1 KB CHR banks.
+    fn character_banks_1k(&self) -> u32 {
+        self.character_banks.max(1) as u32 * 8
+    }
+
+    fn program_offset(&self, addr: u16) -> u32 {
+        let second_last = self.program_banks_8k() - 2;
+        let last = self.program_banks_8k() - 1;
+
+        let bank = match (addr, self.program_mode) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => self.registers[6] as u32,
+            (0xA000..=0xBFFF, _) => self.registers[7] as u32,
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => second_last,
+            _ => last,
+        };
+
+        (bank % self.program_banks_8k()) * 0x2000 + (addr & 0x1FFF) as u32
+    }
+
+    fn character_offset(&self, addr: u16) -> u32 {
+        let addr = if self.character_inversion {
+            addr ^ 0x1000
+        } else {
+            addr
+        };
+
+        let bank = match addr {
+            // 2 KB banks, ignoring the low bit of the bank number
+            0x0000..=0x03FF => self.registers[0] & 0xFE,
+            0x0400..=0x07FF => self.registers[0] | 0x01,
+            0x0800..=0x0BFF => self.registers[1] & 0xFE,
+            0x0C00..=0x0FFF => self.registers[1] | 0x01,
+            // 1 KB banks
+            0x1000..=0x13FF => self.registers[2],
+            0x1400..=0x17FF => self.registers[3],
+            0x1800..=0x1BFF => self.registers[4],
+            _ => self.registers[5],
+        } as u32;
+
+        (bank % self.character_banks_1k()) * 0x0400 + (addr & 0x03FF) as u32
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        let even = addr & 0x01 == 0;
+
+        match (addr, even) {
+            (0x8000..=0x9FFF, true) => {
+                self.target_register = data & 0x07;
+                self.program_mode = data & 0x40 != 0;
+                self.character_inversion = data & 0x80 != 0;
+            }
+            (0x8000..=0x9FFF, false) => {
+                self.registers[self.target_register as usize] = data;
+            }
+            (0xA000..=0xBFFF, true) => {
+                self.mirror = if data & 0x01 == 0 {
+                    CartridgeMirror::Vertical
+                } else {
+                    CartridgeMirror::Horizontal
+                };
+            }
+            (0xA000..=0xBFFF, false) => {
+                self.program_ram_enabled = data & 0x80 != 0;
+                self.program_ram_write_protected = data & 0x40 != 0;
+            }
+            (0xC000..=0xDFFF, true) => self.irq_latch = data,
+            (0xC000..=0xDFFF, false) => {
+                // the counter is reloaded on the next scanline
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (_, true) => {
+                // disabling also acknowledges pending interrupts
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            (_, false) => self.irq_enabled = true,
+        }
+    }
+}
+
+impl Mapper for Mapper4 {
+    fn cpu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.program_ram_enabled => Mapped::Ram((addr & 0x1FFF) as u32),
+            0x8000..=0xFFFF => Mapped::Memory(self.program_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.program_ram_enabled && !self.program_ram_write_protected => {
+                Mapped::Ram((addr & 0x1FFF) as u32)
+            }
+            0x8000..=0xFFFF => {
+                self.write_register(addr, data);
+                Mapped::Register
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn ppu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x0000..=0x1FFF => Mapped::Memory(self.character_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn mirror(&self) -> Option<CartridgeMirror> {
+        Some(self.mirror)
+    }
+
+    fn reset(&mut self) {
+        self.target_register = 0;
+        self.program_mode = false;
+        self.character_inversion = false;
+        self.registers = [0, 2, 4, 5, 6, 7, 0, 1];
+        self.mirror = CartridgeMirror::Vertical;
+        self.program_ram_enabled = true;
+        self.program_ram_write_protected = false;
+        self.irq_latch = 0;
+        self.irq_counter = 0;
+        self.irq_reload = false;
+        self.irq_enabled = false;
+        self.irq_pending = false;
+    }
+
+    /// Clocks the scanline counter.
+    fn ppu_a12_rise(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.target_register);
+        state.write_bool(self.program_mode);
+        state.write_bool(self.character_inversion);
+        state.write_bytes(&self.registers);
+
+        state.write_bool(self.mirror == CartridgeMirror::Horizontal);
+        state.write_bool(self.program_ram_enabled);
+        state.write_bool(self.program_ram_write_protected);
+
+        state.write_u8(self.irq_latch);
+        state.write_u8(self.irq_counter);
+        state.write_bool(self.irq_reload);
+        state.write_bool(self.irq_enabled);
+        state.write_bool(self.irq_pending);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.target_register = state.read_u8()?;
+        self.program_mode = state.read_bool()?;
+        self.character_inversion = state.read_bool()?;
+        state.read_bytes(&mut self.registers)?;
+
+        self.mirror = if state.read_bool()? {
+            CartridgeMirror::Horizontal
+        } else {
+            CartridgeMirror::Vertical
+        };
+        self.program_ram_enabled = state.read_bool()?;
+        self.program_ram_write_protected = state.read_bool()?;
+
+        self.irq_latch = state.read_u8()?;
+        self.irq_counter = state.read_u8()?;
+        self.irq_reload = state.read_bool()?;
+        self.irq_enabled = state.read_bool()?;
+        self.irq_pending = state.read_bool()?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_scanline_irq() {
+    let mut mapper = Mapper4::new(8, 8);
+
+    // IRQ latch = 2, reload, enable
+    mapper.cpu_map_write(0xC000, 2);
+    mapper.cpu_map_write(0xC001, 0);
+    mapper.cpu_map_write(0xE001, 0);
+
+    // the first rise reloads the counter, the next two count it down
+    mapper.ppu_a12_rise();
+    mapper.ppu_a12_rise();
+    assert!(!mapper.irq_pending());
+    mapper.ppu_a12_rise();
+    assert!(mapper.irq_pending());
+
+    // writing to $E000 acknowledges the interrupt
+    mapper.cpu_map_write(0xE000, 0);
+    assert!(!mapper.irq_pending());
+}
+
+#[test]
+fn test_reset() {
+    let mut mapper = Mapper4::new(8, 8);
+
+    // horizontal mirroring, PRG-RAM disabled
+    mapper.cpu_map_write(0xA000, 1);
+    mapper.cpu_map_write(0xA001, 0x00);
+    assert_eq!(mapper.cpu_map_read(0x6000), Mapped::Unmapped);
+
+    mapper.reset();
+    assert_eq!(mapper.mirror(), Some(CartridgeMirror::Vertical));
+    assert_eq!(mapper.cpu_map_read(0x6000), Mapped::Ram(0));
+}
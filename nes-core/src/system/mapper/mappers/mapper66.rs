@@ -0,0 +1,81 @@
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::mapper::{Mapped, Mapper};
+
+/// GxROM.
+///
+/// Writes to $8000-$FFFF select the 32 KB PRG bank (bits 4-5) and the
+/// 8 KB CHR bank (bits 0-1). Has no PRG-RAM, $6000-$7FFF is open bus.
+#[derive(Debug, Clone)]
+pub struct Mapper66 {
+    program_banks: u8,
+    character_banks: u8,
+
+    program_bank: u8,
+    character_bank: u8,
+}
+
+impl Mapper66 {
+    pub fn new(program_banks: u8, character_banks: u8) -> Self {
+        Mapper66 {
+            program_banks,
+            character_banks,
+            program_bank: 0,
+            character_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Mapper66 {
+    fn cpu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x8000..=0xFFFF => {
+                // amount of 32 KB banks
+                let banks = (self.program_banks / 2).max(1);
+                let bank = self.program_bank % banks;
+                Mapped::Memory(bank as u32 * 0x8000 + (addr & 0x7FFF) as u32)
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> Mapped {
+        match addr {
+            0x8000..=0xFFFF => {
+                self.program_bank = (data >> 4) & 0x03;
+                self.character_bank = data & 0x03;
+                Mapped::Register
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn ppu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x0000..=0x1FFF => {
+                let bank = self.character_bank % self.character_banks.max(1);
+                Mapped::Memory(bank as u32 * 0x2000 + addr as u32)
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.program_bank = 0;
+        self.character_bank = 0;
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        true
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.program_bank);
+        state.write_u8(self.character_bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.program_bank = state.read_u8()?;
+        self.character_bank = state.read_u8()?;
+        Ok(())
+    }
+}
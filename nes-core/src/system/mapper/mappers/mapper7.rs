@@ -0,0 +1,86 @@
+use crate::cartridge::CartridgeMirror;
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::system::mapper::{Mapped, Mapper};
+
+/// AxROM.
+///
+/// Writes to $8000-$FFFF select the 32 KB PRG bank (bits 0-2) and which
+/// nametable is used by the one-screen mirroring (bit 4). Has 8 KB of
+/// CHR-RAM and no PRG-RAM, $6000-$7FFF is open bus.
+#[derive(Debug, Clone)]
+pub struct Mapper7 {
+    program_banks: u8,
+
+    program_bank: u8,
+    mirror: CartridgeMirror,
+}
+
+impl Mapper7 {
+    pub fn new(program_banks: u8, _character_banks: u8) -> Self {
+        Mapper7 {
+            program_banks,
+            program_bank: 0,
+            mirror: CartridgeMirror::OneScreenLow,
+        }
+    }
+}
+
+impl Mapper for Mapper7 {
+    fn cpu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x8000..=0xFFFF => {
+                // amount of 32 KB banks
+                let banks = (self.program_banks / 2).max(1);
+                let bank = self.program_bank % banks;
+                Mapped::Memory(bank as u32 * 0x8000 + (addr & 0x7FFF) as u32)
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> Mapped {
+        match addr {
+            0x8000..=0xFFFF => {
+                self.program_bank = data & 0x07;
+                self.mirror = if data & 0x10 == 0 {
+                    CartridgeMirror::OneScreenLow
+                } else {
+                    CartridgeMirror::OneScreenHigh
+                };
+                Mapped::Register
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn ppu_map_read(&self, addr: u16) -> Mapped {
+        match addr {
+            0x0000..=0x1FFF => Mapped::Memory(addr as u32),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn mirror(&self) -> Option<CartridgeMirror> {
+        Some(self.mirror)
+    }
+
+    fn reset(&mut self) {
+        self.program_bank = 0;
+        self.mirror = CartridgeMirror::OneScreenLow;
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.program_bank);
+        state.write_bool(self.mirror == CartridgeMirror::OneScreenHigh);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.program_bank = state.read_u8()?;
+        self.mirror = if state.read_bool()? {
+            CartridgeMirror::OneScreenHigh
+        } else {
+            CartridgeMirror::OneScreenLow
+        };
+        Ok(())
+    }
+}
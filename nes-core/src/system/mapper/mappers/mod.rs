@@ -4,5 +4,17 @@
 //! add more mappers.
 
 mod mapper0;
+mod mapper1;
+mod mapper2;
+mod mapper3;
+mod mapper4;
+mod mapper66;
+mod mapper7;
 
 pub use mapper0::Mapper0;
+pub use mapper1::Mapper1;
+pub use mapper2::Mapper2;
+pub use mapper3::Mapper3;
+pub use mapper4::Mapper4;
+pub use mapper66::Mapper66;
+pub use mapper7::Mapper7;
@@ -2,21 +2,114 @@
 //!
 //! Mappers are circuits contained within NES cartridges that allow
 //! games to expand the NES' capabilities and bypass its limitations.
+//!
+//! Most mappers contain registers that are written to by the CPU
+//! through the same addresses used to read the cartridge's ROM.
+//! These registers may switch which banks of memory are visible to
+//! the CPU and PPU, or change how the nametables are mirrored.
 
 pub mod mappers;
 
 use std::fmt::Debug;
 
+use crate::cartridge::CartridgeMirror;
+use crate::state::{StateError, StateReader, StateWriter};
+
+/// Result of mapping an address through a mapper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mapped {
+    /// The address corresponds to the given offset in the cartridge's
+    /// memory: program memory for the CPU, character memory for the PPU.
+    Memory(u32),
+    /// The address corresponds to the given offset in the cartridge's
+    /// program RAM, usually mapped to $6000-$7FFF.
+    Ram(u32),
+    /// The write was latched into one of the mapper's registers and
+    /// must not change the cartridge's memory.
+    Register,
+    /// The mapper does not respond to the address.
+    Unmapped,
+}
+
 /// Mapper trait.
 ///
 /// Allows the creation of generic Mappers.
-pub trait Mapper: Debug {
+pub trait Mapper: Debug + MapperClone {
     /// Map reads from the CPU.
-    fn cpu_map_read(&self, addr: u16) -> Option<u32>;
-    /// Map writes from the CPU.
-    fn cpu_map_write(&self, addr: u16, data: u8) -> Option<u32>;
+    fn cpu_map_read(&self, addr: u16) -> Mapped;
+    /// Map writes from the CPU. May change the mapper's registers.
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> Mapped;
     /// Map reads from the PPU.
-    fn ppu_map_read(&self, addr: u16) -> Option<u32>;
+    fn ppu_map_read(&self, addr: u16) -> Mapped;
     /// Map writes from the PPU.
-    fn ppu_map_write(&self, addr: u16) -> Option<u32>;
+    ///
+    /// Mapped the same way as reads by default. The cartridge ignores
+    /// writes to CHR-ROM.
+    fn ppu_map_write(&mut self, addr: u16) -> Mapped {
+        self.ppu_map_read(addr)
+    }
+
+    /// Nametable mirroring selected by the mapper.
+    ///
+    /// Returns `None` if the mirroring is hardwired by the cartridge,
+    /// in which case the mirroring from the cartridge's header is used.
+    fn mirror(&self) -> Option<CartridgeMirror> {
+        None
+    }
+
+    /// Puts the mapper's registers back into their power-up state.
+    fn reset(&mut self) {}
+
+    /// Called by the PPU when bit 12 of its address bus (A12) rises.
+    ///
+    /// While rendering with the background and sprites using different
+    /// pattern tables, this happens once per scanline, which allows
+    /// mappers to count scanlines.
+    fn ppu_a12_rise(&mut self) {}
+
+    /// Returns true while the mapper asserts the CPU's IRQ line.
+    fn irq_pending(&self) -> bool {
+        false
+    }
+
+    /// Returns true if the board has bus conflicts.
+    ///
+    /// On such boards the ROM keeps driving the data bus while the CPU
+    /// writes to a register, so the value latched by the mapper is the
+    /// logical AND of the written value and the byte stored in ROM at
+    /// the same address.
+    fn bus_conflicts(&self) -> bool {
+        false
+    }
+
+    /// Writes the mapper's registers to a save state.
+    fn save_state(&self, _state: &mut StateWriter) {}
+
+    /// Restores the mapper's registers from a save state, reading
+    /// them in the same order they were written.
+    fn load_state(&mut self, _state: &mut StateReader) -> Result<(), StateError> {
+        Ok(())
+    }
+}
+
+/// Allows cloning boxed mappers, which is needed to clone a `Cartridge`.
+///
+/// Automatically implemented for every mapper that implements `Clone`.
+pub trait MapperClone {
+    fn clone_box(&self) -> Box<dyn Mapper>;
+}
+
+impl<T> MapperClone for T
+where
+    T: 'static + Mapper + Clone,
+{
+    fn clone_box(&self) -> Box<dyn Mapper> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Mapper> {
+    fn clone(&self) -> Box<dyn Mapper> {
+        self.clone_box()
+    }
 }
@@ -1,21 +1,47 @@
+pub(crate) mod apu;
 pub(crate) mod bus;
 pub(crate) mod cpu;
 pub(crate) mod mapper;
 pub(crate) mod ppu;
 pub(crate) mod ram;
 
-use crate::cartridge::Cartridge;
-use crate::controller::Controller;
-use crate::screen::NesScreen;
+use std::ops::RangeInclusive;
+
+use crate::cartridge::{Cartridge, CartridgeError, CartridgeMetadata};
+use crate::controller::{Controller, InputDevice, Port};
+use crate::debugger::Interrupt;
+use crate::disasm;
+use crate::recorder::Recorder;
+use crate::region::Region;
+use crate::screen::{NametableScreen, NesScreen, PaletteScreen, PatternTableScreen, SpriteScreen};
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::trace::{TraceHook, Tracer};
+use bus::MemoryAccess;
 use cpu::Cpu;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Default)]
 pub(crate) struct System {
     cpu: Cpu,
 
     /// Used by the `self.system_clock` function
     /// to have the PPU clock faster than the CPU.
     clock_counter: u32,
+    /// CPU cycles since power-up, including the ones
+    /// stolen by the DMA
+    cpu_cycles: u64,
+
+    /// Called before each instruction is executed
+    trace_hook: Option<TraceHook>,
+    /// Logs each instruction before it's executed
+    tracer: Option<Tracer>,
+    /// Writes each completed frame to a video
+    recorder: Option<Recorder>,
+
+    /// Interrupt taken by the CPU during the last clock cycle
+    interrupt: Option<Interrupt>,
+
+    /// Sets the speed of the CPU relative to the PPU
+    region: Region,
 }
 
 impl System {
@@ -23,8 +49,16 @@ impl System {
         let mut system = Self {
             cpu: Cpu::new(),
             clock_counter: 0,
+            cpu_cycles: 0,
+            trace_hook: None,
+            tracer: None,
+            recorder: None,
+            interrupt: None,
+            region: Region::Ntsc,
         };
+        let region = Region::from_cartridge(cartridge.metadata().region);
         system.cpu.bus.insert_cartridge(cartridge);
+        system.set_region(region);
         system.reset();
         system
     }
@@ -33,8 +67,12 @@ impl System {
         self.cpu.bus.ppu.screen()
     }
 
+    pub(crate) fn mut_screen(&mut self) -> &mut NesScreen {
+        self.cpu.bus.ppu.mut_screen()
+    }
+
     pub fn next_frame(&mut self) -> &NesScreen {
-        while !self.cpu.bus.ppu.screen_ready() {
+        while !self.screen_ready() {
             self.clock();
         }
 
@@ -49,43 +87,413 @@ impl System {
         &mut self.cpu.bus.controllers
     }
 
+    pub fn input_device(&self, port: Port) -> Option<&dyn InputDevice> {
+        self.cpu.bus.devices[port as usize].as_deref()
+    }
+
+    pub fn mut_input_device(&mut self, port: Port) -> Option<&mut dyn InputDevice> {
+        match &mut self.cpu.bus.devices[port as usize] {
+            Some(device) => Some(device.as_mut()),
+            None => None,
+        }
+    }
+
+    pub fn set_input_device(
+        &mut self,
+        port: Port,
+        device: Option<Box<dyn InputDevice>>,
+    ) -> Option<Box<dyn InputDevice>> {
+        std::mem::replace(&mut self.cpu.bus.devices[port as usize], device)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.cpu.bus.apu.sample_rate()
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.cpu.bus.apu.set_sample_rate(sample_rate);
+    }
+
+    pub fn take_audio_samples(&mut self) -> Vec<f32> {
+        self.cpu.bus.apu.take_samples()
+    }
+
+    pub fn cpu_jammed(&self) -> bool {
+        self.cpu.jammed()
+    }
+
+    pub fn cartridge_metadata(&self) -> CartridgeMetadata {
+        self.cpu
+            .bus
+            .cartridge
+            .as_ref()
+            .map(|cart| *cart.borrow().metadata())
+            .expect("No cartridge inserted!")
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.cpu.bus.ppu.set_region(region);
+        self.cpu.bus.apu.set_region(region);
+    }
+
+    pub fn cpu_cycles(&self) -> u64 {
+        self.cpu_cycles
+    }
+
+    pub fn set_trace_hook(&mut self, hook: Option<TraceHook>) {
+        self.trace_hook = hook;
+    }
+
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) -> Option<Tracer> {
+        std::mem::replace(&mut self.tracer, tracer)
+    }
+
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
+    /// Replaces the recorder, starting the new one. Audio samples are
+    /// only kept for the recorder while there is one.
+    pub fn set_recorder(&mut self, mut recorder: Option<Recorder>) -> Option<Recorder> {
+        if let Some(recorder) = &mut recorder {
+            recorder.start(self.region, self.sample_rate());
+        }
+        self.cpu.bus.apu.recorded_samples = recorder.is_some().then(Vec::new);
+        std::mem::replace(&mut self.recorder, recorder)
+    }
+
+    pub fn battery_ram(&self) -> Option<Vec<u8>> {
+        let cart = self.cpu.bus.cartridge.as_ref()?.borrow();
+        cart.battery_ram().map(<[u8]>::to_vec)
+    }
+
+    pub fn load_battery_ram(&mut self, data: &[u8]) -> Result<(), CartridgeError> {
+        match &self.cpu.bus.cartridge {
+            Some(cart) => cart.borrow_mut().load_battery_ram(data),
+            None => Err(CartridgeError::SaveError("no cartridge inserted".into())),
+        }
+    }
+
+    /// Snapshot of the whole console, excluding the ROM.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::new(self.rom_crc32());
+        state.write_u8(match self.region {
+            Region::Ntsc => 0,
+            Region::Pal => 1,
+            Region::Dendy => 2,
+        });
+        self.cpu.save_state(&mut state);
+        self.cpu.bus.save_state(&mut state);
+        state.write_u32(self.clock_counter);
+        state.write_u64(self.cpu_cycles);
+        state.finish()
+    }
+
+    /// Restores a snapshot made by `save_state`.
+    ///
+    /// If the state turns out to be corrupted, the console is left as it
+    /// was before the call.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut state = StateReader::new(data, self.rom_crc32())?;
+        let backup = self.save_state();
+
+        let result = self.read_state(&mut state).and_then(|()| state.finish());
+        if result.is_err() {
+            let mut backup = StateReader::new(&backup, self.rom_crc32())
+                .expect("backup state should have a valid header");
+            self.read_state(&mut backup)
+                .expect("backup state should be valid");
+        }
+        result
+    }
+
+    fn read_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        // the timings of the PPU and APU restored below depend on the region
+        self.set_region(match state.read_u8()? {
+            0 => Region::Ntsc,
+            1 => Region::Pal,
+            2 => Region::Dendy,
+            _ => return Err(StateError::CorruptedError),
+        });
+        self.cpu.load_state(state)?;
+        self.cpu.bus.load_state(state)?;
+        self.clock_counter = state.read_u32()?;
+        self.cpu_cycles = state.read_u64()?;
+        Ok(())
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.cpu.pc
+    }
+
+    pub fn peek(&self, addr: u16) -> u8 {
+        self.cpu.bus.peek(addr)
+    }
+
+    pub fn peek_ppu(&self, addr: u16) -> u8 {
+        self.cpu.bus.ppu.ppu_peek(addr)
+    }
+
+    pub fn peek_oam(&self, index: u8) -> u8 {
+        self.cpu.bus.ppu.oam.get_byte(index)
+    }
+
+    pub fn draw_pattern_table(&self, table: u8, palette: u8) -> PatternTableScreen {
+        self.cpu.bus.ppu.draw_pattern_table(table, palette)
+    }
+
+    pub fn draw_nametables(&self) -> NametableScreen {
+        self.cpu.bus.ppu.draw_nametables()
+    }
+
+    pub fn draw_sprites(&self) -> SpriteScreen {
+        self.cpu.bus.ppu.draw_sprites()
+    }
+
+    pub fn draw_palettes(&self) -> PaletteScreen {
+        self.cpu.bus.ppu.draw_palettes()
+    }
+
+    pub fn poke(&mut self, addr: u16, data: u8) -> bool {
+        self.cpu.bus.poke(addr, data)
+    }
+
+    pub fn poke_ppu(&mut self, addr: u16, data: u8) {
+        self.cpu.bus.ppu.ppu_write(addr, data);
+    }
+
+    pub fn poke_oam(&mut self, index: u8, data: u8) {
+        self.cpu.bus.ppu.oam.set_byte(index, data);
+    }
+
+    /// Scanline and dot the PPU is on.
+    pub fn ppu_position(&self) -> (i16, i16) {
+        (self.cpu.bus.ppu.scanline(), self.cpu.bus.ppu.dot())
+    }
+
+    /// Returns true once after each frame is completed, after giving
+    /// the frame to the recorder.
+    pub fn screen_ready(&mut self) -> bool {
+        let ready = self.cpu.bus.ppu.screen_ready();
+        if let (true, Some(recorder)) = (ready, &mut self.recorder) {
+            let samples = self
+                .cpu
+                .bus
+                .apu
+                .recorded_samples
+                .as_mut()
+                .map(std::mem::take)
+                .unwrap_or_default();
+            recorder.record_frame(self.cpu.bus.ppu.screen(), &samples);
+        }
+        ready
+    }
+
+    /// Interrupt taken by the CPU during the last clock cycle.
+    pub fn interrupt(&self) -> Option<Interrupt> {
+        self.interrupt
+    }
+
+    /// Starts or stops recording the reads and writes made through
+    /// the CPU's bus.
+    pub fn record_accesses(&mut self, record: bool) {
+        self.cpu.bus.accesses = record.then(Vec::new);
+    }
+
+    /// Takes the reads and writes recorded since the last call.
+    pub fn take_accesses(&mut self) -> Vec<MemoryAccess> {
+        self.cpu
+            .bus
+            .accesses
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    pub fn disassemble(&self, range: RangeInclusive<u16>) -> Vec<disasm::Instruction> {
+        let (start, end) = range.into_inner();
+        if start > end {
+            return Vec::new();
+        }
+
+        // two more bytes, so the last instruction is complete
+        let bytes: Vec<u8> = (0..=(end - start) as u32 + 2)
+            .map(|offset| self.cpu.bus.peek(start.wrapping_add(offset as u16)))
+            .collect();
+
+        let mut instructions = disasm::disassemble(&bytes, start);
+        instructions.retain(|i| i.addr.wrapping_sub(start) <= end - start);
+        instructions
+    }
+
+    fn rom_crc32(&self) -> u32 {
+        self.cpu
+            .bus
+            .cartridge
+            .as_ref()
+            .map_or(0, |cart| cart.borrow().rom_crc32())
+    }
+
+    pub(crate) fn rom_md5(&self) -> [u8; 16] {
+        self.cpu
+            .bus
+            .cartridge
+            .as_ref()
+            .map_or([0; 16], |cart| cart.borrow().rom_md5())
+    }
+
     /// **System clock cycle**
     ///
     /// Executes a clock cycle for all parts of the console's internal system,
-    /// namely, the CPU and PPU.
+    /// namely, the CPU, PPU and APU.
     pub fn clock(&mut self) {
+        // traced before the PPU is clocked, so the dot of the trace is the
+        // one the instruction starts on
+        if (self.trace_hook.is_some() || self.tracer.is_some()) && self.instruction_starts() {
+            let trace = self.cpu.trace(self.cpu_cycles);
+            if let Some(hook) = &mut self.trace_hook {
+                hook(&trace);
+            }
+            if let Some(tracer) = &mut self.tracer {
+                tracer.trace(&trace, &self.cpu.bus);
+            }
+        }
+
+        self.interrupt = None;
         self.cpu.bus.ppu.clock();
 
-        if self.clock_counter % 3 == 0 {
+        if self.cpu_clocked() {
+            // the APU runs at the same speed as the CPU, even during DMA
+            self.cpu.bus.clock_apu();
+
             // it may be time to clock the CPU, depending on the status of the DMA
             // let dma = &mut self.cpu.bus.dma;
 
-            let dma_treated = self.cpu.bus.treat_dma_transfer(self.clock_counter);
+            let (ppu_cycles, cpu_cycles) = self.region.clock_ratio();
+            let cpu_cycle = self.clock_counter as u64 * cpu_cycles / ppu_cycles;
+            let dma_treated = self.cpu.bus.treat_dma_transfer(cpu_cycle);
             if !dma_treated {
                 // the DMA isn't transferring data, the CPU is allowed to clock
                 self.cpu.clock();
+
+                // interrupt requests are only accepted between instructions
+                if self.cpu.complete() && self.cpu.bus.irq_pending() && self.cpu.irq() {
+                    self.interrupt = Some(Interrupt::Irq);
+                }
             }
         }
 
         if self.cpu.bus.ppu.interrupt_sent() {
             self.cpu.bus.ppu.interrupt_done();
-            self.cpu.nmi();
+            if self.cpu.nmi() {
+                self.interrupt = Some(Interrupt::Nmi);
+            }
+        }
+
+        if self.cpu_clocked() {
+            self.cpu_cycles += 1;
         }
 
         // TODO: Test if wrapping_add breaks anything
         self.clock_counter = self.clock_counter.wrapping_add(1);
     }
 
+    /// Returns true if the CPU starts executing an instruction during
+    /// the next clock cycle.
+    pub fn instruction_starts(&self) -> bool {
+        self.cpu_clocked()
+            && !self.cpu.bus.dma.transfer
+            && self.cpu.complete()
+            && !self.cpu.jammed()
+    }
+
+    /// Returns true if the CPU and the APU are clocked during the next
+    /// clock cycle: once every 3 cycles on NTSC and Dendy consoles, and
+    /// 5 times every 16 cycles on PAL consoles.
+    fn cpu_clocked(&self) -> bool {
+        let (ppu_cycles, cpu_cycles) = self.region.clock_ratio();
+        (self.clock_counter as u64 * cpu_cycles) % ppu_cycles < cpu_cycles
+    }
+
+    /// Turns the console off and on. Every component starts over from
+    /// its power-up state, only the cartridge's RAM is kept.
+    pub fn power_cycle(&mut self) {
+        let cartridge = self
+            .cpu
+            .bus
+            .cartridge
+            .as_ref()
+            .map(|cart| cart.borrow().clone());
+        let sample_rate = self.sample_rate();
+        let recorded_samples = self.cpu.bus.apu.recorded_samples.take();
+        // the devices stay plugged in
+        let devices = std::mem::take(&mut self.cpu.bus.devices);
+
+        self.cpu = Cpu::new();
+        if let Some(cartridge) = cartridge {
+            self.cpu.bus.insert_cartridge(cartridge);
+        }
+        self.set_region(self.region);
+        self.set_sample_rate(sample_rate);
+        self.cpu.bus.apu.recorded_samples = recorded_samples;
+        self.cpu.bus.devices = devices;
+
+        self.cpu_cycles = 0;
+        self.reset();
+    }
+
     /// **System reset**
     ///
-    /// Resets the CPU and the PPU.
+    /// Resets the cartridge's mapper, the CPU, the PPU and the APU.
     pub fn reset(&mut self) {
-        self.cpu.reset();
+        // the mapper must be reset before the CPU reads the reset vector
         self.cpu.bus.reset();
+        self.cpu.reset();
         self.clock_counter = 0;
     }
 }
 
+impl Clone for System {
+    /// Clones the console. The trace hook, the tracer and the recorder
+    /// are not cloned.
+    fn clone(&self) -> Self {
+        Self {
+            cpu: self.cpu.clone(),
+            clock_counter: self.clock_counter,
+            cpu_cycles: self.cpu_cycles,
+            trace_hook: None,
+            tracer: None,
+            recorder: None,
+            interrupt: None,
+            region: self.region,
+        }
+    }
+}
+
+impl std::fmt::Debug for System {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("System")
+            .field("cpu", &self.cpu)
+            .field("clock_counter", &self.clock_counter)
+            .field("cpu_cycles", &self.cpu_cycles)
+            .field("trace_hook", &self.trace_hook.is_some())
+            .field("tracer", &self.tracer)
+            .field("recorder", &self.recorder)
+            .field("interrupt", &self.interrupt)
+            .field("region", &self.region)
+            .finish()
+    }
+}
+
 impl std::fmt::Display for System {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.cpu.bus.cartridge {
@@ -97,3 +505,95 @@ impl std::fmt::Display for System {
         }
     }
 }
+
+/// Runs nestest in automation mode, returning the console after the
+/// last test and the trace of every instruction.
+#[cfg(test)]
+fn nestest_trace() -> (System, Vec<crate::trace::CpuTrace>) {
+    use crate::trace::CpuTrace;
+    use std::{cell::RefCell, rc::Rc};
+
+    let mut system = System::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    // automation mode runs every test without needing the PPU
+    system.cpu.pc = 0xC000;
+
+    let traces = Rc::new(RefCell::new(Vec::new()));
+    let hook_traces = traces.clone();
+    system.set_trace_hook(Some(Box::new(move |trace| {
+        hook_traces.borrow_mut().push(*trace)
+    })));
+
+    // the tests end with the RTS at $C66E
+    let done =
+        |traces: &RefCell<Vec<CpuTrace>>| traces.borrow().last().map(|t| t.pc) == Some(0xC66E);
+    while !(done(&traces) && system.cpu.complete()) {
+        system.clock();
+    }
+    system.set_trace_hook(None);
+
+    let traces = traces.take();
+    (system, traces)
+}
+
+/// Removes the disassembly from a line of nestest's log.
+#[cfg(test)]
+fn strip_disassembly(line: &str) -> String {
+    format!("{} {}", &line[..15], &line[48..])
+}
+
+#[test]
+fn test_nestest() {
+    let (system, traces) = nestest_trace();
+
+    // nestest stores the number of the first failed test at $02 and $03
+    assert_eq!(system.cpu.bus.peek(0x0002), 0x00);
+    assert_eq!(system.cpu.bus.peek(0x0003), 0x00);
+
+    // first and last lines of the canonical log
+    assert_eq!(traces.len(), 8991);
+    assert_eq!(
+        traces[0].to_string(),
+        strip_disassembly("C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7")
+    );
+    assert_eq!(
+        traces[8990].to_string(),
+        strip_disassembly("C66E  60        RTS                             A:00 X:FF Y:15 P:27 SP:FD PPU:233,209 CYC:26554")
+    );
+}
+
+/// Compares the trace against nestest's log, line by line.
+#[test]
+fn test_nestest_log() {
+    let log = std::fs::read_to_string("test_data/nestest.log").unwrap();
+    let (_, traces) = nestest_trace();
+
+    for (i, (trace, line)) in traces.iter().zip(log.lines()).enumerate() {
+        assert_eq!(
+            trace.to_string(),
+            strip_disassembly(line),
+            "first divergence at line {}",
+            i + 1
+        );
+    }
+    assert_eq!(traces.len(), log.lines().count());
+}
+
+#[test]
+fn test_peek_poke() {
+    let mut system = System::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+
+    // RAM is mirrored every 2 KB, the ROM and the registers can't be poked
+    assert!(system.poke(0x0010, 0x42));
+    assert_eq!(system.peek(0x0810), 0x42);
+    assert!(!system.poke(0x8000, 0x42));
+    assert!(!system.poke(0x2000, 0x42));
+
+    // $3F10 mirrors the background color
+    system.poke_ppu(0x3F10, 0x21);
+    assert_eq!(system.peek_ppu(0x3F00), 0x21);
+    system.poke_ppu(0x2001, 0x24);
+    assert_eq!(system.peek_ppu(0x2001), 0x24);
+
+    system.poke_oam(5, 0x99);
+    assert_eq!(system.peek_oam(5), 0x99);
+}
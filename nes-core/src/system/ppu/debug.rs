@@ -0,0 +1,202 @@
+//! Debug views of the PPU's memory.
+//!
+//! Each view is drawn into a `Screen` when requested, using the
+//! palettes currently loaded in the PPU. Memory is read without side
+//! effects, so the views can be drawn while the game runs.
+
+use crate::screen::pixel::{self, Pixel};
+use crate::screen::{NametableScreen, PaletteScreen, PatternTableScreen, Screen, SpriteScreen};
+
+use super::registers::ControlReg;
+use super::Ppu;
+
+impl Ppu {
+    /// Draws the 256 tiles of a pattern table, in rows of 16 tiles.
+    ///
+    /// `palette` ranges from 0 to 7, the first four are the background
+    /// palettes and the last four the sprite palettes.
+    pub fn draw_pattern_table(&self, table: u8, palette: u8) -> PatternTableScreen {
+        let mut screen = Screen::new();
+        let base = u16::from(table & 0x01) << 12;
+
+        for tile in 0..256 {
+            let tile_addr = base + tile * 16;
+            let (row, col) = (tile as usize / 16 * 8, tile as usize % 16 * 8);
+
+            for y in 0..8 {
+                for x in 0..8 {
+                    let index = self.pattern_pixel(tile_addr, y, x);
+                    screen.set_pixel(
+                        (row + y as usize, col + x as usize),
+                        self.color_from_palette(palette & 0x07, index),
+                    );
+                }
+            }
+        }
+
+        screen.switch_buffer();
+        screen
+    }
+
+    /// Draws the four nametables, as mirrored by the cartridge, in a
+    /// 2x2 grid. The part of the nametables shown at the start of the
+    /// frame is outlined by inverting the color of its edges.
+    pub fn draw_nametables(&self) -> NametableScreen {
+        let mut screen = Screen::new();
+
+        // the scroll position at the start of the frame is held by the
+        // temporary address, copied into the VRAM address on the pre-render line
+        let scroll_x = usize::from(self.tram_addr.nametable_x()) * 256
+            + self.tram_addr.coarse_x() as usize * 8
+            + self.fine_x as usize;
+        let scroll_y = usize::from(self.tram_addr.nametable_y()) * 240
+            + self.tram_addr.coarse_y() as usize * 8
+            + self.tram_addr.fine_y() as usize;
+        let outlined = |(row, col): (usize, usize)| {
+            let row = (row + 480 - scroll_y) % 480;
+            let col = (col + 512 - scroll_x) % 512;
+            row < 240 && col < 256 && (row == 0 || row == 239 || col == 0 || col == 255)
+        };
+
+        let pattern_base = u16::from(self.control.contains(ControlReg::PATTERN_BACKGROUND)) << 12;
+
+        for nametable in 0..4u16 {
+            let base = 0x2000 + nametable * 0x0400;
+            let (top, left) = (
+                (nametable >> 1) as usize * 240,
+                (nametable & 1) as usize * 256,
+            );
+
+            for tile_row in 0..30u16 {
+                for tile_col in 0..32u16 {
+                    let tile_id = self.ppu_peek(base + tile_row * 32 + tile_col);
+                    let attribute =
+                        self.ppu_peek(base + 0x03C0 + (tile_row / 4) * 8 + tile_col / 4);
+                    // each attribute byte holds the palettes of 4 areas of 2x2 tiles
+                    let shift = ((tile_row & 0x02) << 1) | (tile_col & 0x02);
+                    let palette = (attribute >> shift) & 0x03;
+                    let tile_addr = pattern_base + u16::from(tile_id) * 16;
+
+                    for y in 0..8 {
+                        for x in 0..8 {
+                            let pos = (
+                                top + (tile_row * 8 + y) as usize,
+                                left + (tile_col * 8 + x) as usize,
+                            );
+                            let index = self.pattern_pixel(tile_addr, y, x);
+                            let mut color = self.color_from_palette(palette, index);
+                            if outlined(pos) {
+                                color = Pixel::new(!color.r, !color.g, !color.b);
+                            }
+                            screen.set_pixel(pos, color);
+                        }
+                    }
+                }
+            }
+        }
+
+        screen.switch_buffer();
+        screen
+    }
+
+    /// Draws the 64 sprites of the OAM in rows of 8, each in a cell of
+    /// 8x16 pixels. Sprites are flipped as they would be on screen, and
+    /// their transparent pixels use the background color.
+    pub fn draw_sprites(&self) -> SpriteScreen {
+        let mut screen = Screen::new();
+        let tall = self.control.contains(ControlReg::SPRITE_SIZE);
+        let height = if tall { 16 } else { 8 };
+
+        for sprite_index in 0..64u8 {
+            let sprite = self.oam.get_entry(sprite_index);
+            let (row, col) = (
+                sprite_index as usize / 8 * 16,
+                sprite_index as usize % 8 * 8,
+            );
+            let flip_horizontal = sprite.attribute & 0x40 != 0;
+            let flip_vertical = sprite.attribute & 0x80 != 0;
+
+            // 8x16 sprites choose their pattern table with bit 0 of the tile id
+            let (base, tile_id) = if tall {
+                (
+                    u16::from(sprite.tile_id & 0x01) << 12,
+                    sprite.tile_id & 0xFE,
+                )
+            } else {
+                let base = u16::from(self.control.contains(ControlReg::PATTERN_SPRITE)) << 12;
+                (base, sprite.tile_id)
+            };
+
+            for y in 0..height {
+                let sprite_y = if flip_vertical { height - 1 - y } else { y };
+                let tile_addr = base + (u16::from(tile_id) + sprite_y / 8) * 16;
+
+                for x in 0..8 {
+                    let sprite_x = if flip_horizontal { 7 - x } else { x };
+                    let index = self.pattern_pixel(tile_addr, sprite_y & 0x07, sprite_x);
+                    screen.set_pixel(
+                        (row + y as usize, col + x as usize),
+                        self.color_from_palette((sprite.attribute & 0x03) + 0x04, index),
+                    );
+                }
+            }
+        }
+
+        screen.switch_buffer();
+        screen
+    }
+
+    /// Draws the 32 entries of the palette memory: the background
+    /// palettes on the first row, the sprite palettes on the second.
+    pub fn draw_palettes(&self) -> PaletteScreen {
+        let mut screen = Screen::new();
+
+        for entry in 0..32u16 {
+            let color = self.ppu_peek(0x3F00 + entry) & 0x3F;
+            screen.set_pixel(
+                (entry as usize / 16, entry as usize % 16),
+                pixel::ALL_COLORS[color as usize],
+            );
+        }
+
+        screen.switch_buffer();
+        screen
+    }
+
+    /// Index, from 0 to 3, of a pixel of the tile at `tile_addr`.
+    fn pattern_pixel(&self, tile_addr: u16, y: u16, x: u16) -> u8 {
+        let low = self.ppu_peek(tile_addr + y);
+        let high = self.ppu_peek(tile_addr + y + 8);
+        let bit = 7 - x;
+
+        ((low >> bit) & 0x01) | (((high >> bit) & 0x01) << 1)
+    }
+}
+
+#[test]
+fn test_debug_views() {
+    use crate::cartridge::Cartridge;
+    use crate::Nes;
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    for _ in 0..10 {
+        nes.next_frame();
+    }
+    nes.poke_ppu(0x3F00, 0x0F);
+    nes.poke_ppu(0x3F13, 0x30);
+
+    let palettes = nes.draw_palettes();
+    assert_eq!(palettes.get_pixel((0, 0)), pixel::ALL_COLORS[0x0F]);
+    assert_eq!(palettes.get_pixel((1, 3)), pixel::ALL_COLORS[0x30]);
+
+    // tile 0 of the sprite pattern table is empty
+    let pattern_table = nes.draw_pattern_table(0, 4);
+    assert_eq!(pattern_table.get_pixel((7, 7)), pixel::ALL_COLORS[0x0F]);
+
+    // the screen isn't scrolled, so the top-left pixel is outlined
+    let nametables = nes.draw_nametables();
+    let backdrop = pixel::ALL_COLORS[0x0F];
+    let inverted = Pixel::new(!backdrop.r, !backdrop.g, !backdrop.b);
+    assert_eq!(nametables.get_pixel((0, 0)), inverted);
+    assert_eq!(nametables.get_pixel((240, 256)), backdrop);
+}
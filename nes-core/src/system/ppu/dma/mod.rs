@@ -1,5 +1,7 @@
 //! Module for the Direct Memory Access (DMA).
 
+use crate::state::{StateError, StateReader, StateWriter};
+
 /// If the CPU receives a write to this address, the DMA is initiated.
 pub const DMA_ADDR: u16 = 0x4014;
 
@@ -25,6 +27,23 @@ impl Dma {
             dummy: true,
         }
     }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.page);
+        state.write_u8(self.addr);
+        state.write_u8(self.data);
+        state.write_bool(self.transfer);
+        state.write_bool(self.dummy);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.page = state.read_u8()?;
+        self.addr = state.read_u8()?;
+        self.data = state.read_u8()?;
+        self.transfer = state.read_bool()?;
+        self.dummy = state.read_bool()?;
+        Ok(())
+    }
 }
 
 impl Default for Dma {
@@ -1,5 +1,6 @@
 //! Module for the Picture Processing Unit.
 
+mod debug;
 pub(crate) mod dma;
 mod oam;
 mod registers;
@@ -12,7 +13,10 @@ use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
 use crate::cartridge::{Cartridge, CartridgeMirror};
+use crate::controller::Beam;
+use crate::region::Region;
 use crate::screen::{pixel, NesScreen};
+use crate::state::{StateError, StateReader, StateWriter};
 use crate::system::ram::{AFTER_RAM_END, RAM_ADDR_END, RAM_ADDR_START};
 
 use oam::*;
@@ -51,6 +55,11 @@ pub struct Ppu {
     nmi: bool,
     cycle: i16,
     scanline: i16,
+    /// Toggled every frame. On NTSC consoles, odd frames are one
+    /// cycle shorter while rendering is enabled.
+    odd_frame: bool,
+    /// Sets the amount of scanlines per frame
+    region: Region,
 
     pub(crate) oam: Oam,
     oam_addr: u8,
@@ -89,6 +98,8 @@ impl Ppu {
             nmi: false,
             cycle: 0,
             scanline: 0,
+            odd_frame: false,
+            region: Region::Ntsc,
             oam: Oam::default(),
             oam_addr: 0,
             status: StatusReg::empty(),
@@ -110,6 +121,7 @@ impl Ppu {
         self.ppu_data_buffer = 0;
         self.scanline = 0;
         self.cycle = 0;
+        self.odd_frame = false;
         self.bg = BackgroundData::default();
         self.fg = ForegroundData::default();
         self.status = StatusReg::empty();
@@ -123,6 +135,35 @@ impl Ppu {
         &self.screen
     }
 
+    pub(crate) fn mut_screen(&mut self) -> &mut NesScreen {
+        &mut self.screen
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    /// Scanline being drawn, from -1 (pre-render) to 260,
+    /// or to 310 on PAL and Dendy consoles.
+    pub fn scanline(&self) -> i16 {
+        self.scanline
+    }
+
+    /// Dot (PPU cycle) within the current scanline, from 0 to 340.
+    pub fn dot(&self) -> i16 {
+        self.cycle
+    }
+
+    /// The frame being drawn and the position of the beam, as seen by
+    /// light guns.
+    pub fn beam(&self) -> Beam<'_> {
+        Beam {
+            frame: self.screen.work_buffer(),
+            scanline: self.scanline,
+            dot: self.cycle,
+        }
+    }
+
     pub fn screen_ready(&mut self) -> bool {
         if self.frame_complete {
             self.frame_complete = false;
@@ -139,7 +180,14 @@ impl Ppu {
         match self.scanline {
             // rendering portion
             (-1..=239) => {
-                if self.scanline == 0 && self.cycle == 0 {
+                let rendering = self.mask.contains(MaskReg::RENDER_BACKGROUND)
+                    || self.mask.contains(MaskReg::RENDER_SPRITES);
+                if self.scanline == 0
+                    && self.cycle == 0
+                    && self.odd_frame
+                    && rendering
+                    && self.region == Region::Ntsc
+                {
                     // "odd frame" cycle skip
                     self.cycle = 1;
                 }
@@ -377,9 +425,8 @@ impl Ppu {
                     }
                 }
             }
-            240 => { /* do nothing! */ }
-            (241..=260) => {
-                if self.scanline == 241 && self.cycle == 1 {
+            scanline if scanline == self.region.vblank_scanline() => {
+                if self.cycle == 1 {
                     self.status.set(StatusReg::VERTICAL_BLANK, true);
 
                     // PPU has finished drawing, send interrupt signal to the CPU.
@@ -393,6 +440,8 @@ impl Ppu {
             _ => {}
         }
 
+        self.clock_a12();
+
         let mut bg_pixel: u8 = 0; // 2 bit pixel index
         let mut bg_palette: u8 = 0; // 3 bit palette index
 
@@ -529,13 +578,48 @@ impl Ppu {
             self.cycle = 0;
 
             self.scanline += 1;
-            if self.scanline >= 261 {
+            if self.scanline > self.region.last_scanline() {
                 self.scanline = -1;
+                self.odd_frame = !self.odd_frame;
                 self.frame_complete = true;
             }
         }
     }
 
+    /// Notifies the cartridge of rises of the A12 line of the PPU's address bus.
+    ///
+    /// The sprite patterns are not fetched in the same order as the NES,
+    /// so the rises are emulated instead of detected: while rendering,
+    /// A12 rises once per scanline, when the PPU goes from fetching the
+    /// pattern table at $0000 to fetching the one at $1000.
+    fn clock_a12(&mut self) {
+        let rendering = self.mask.contains(MaskReg::RENDER_BACKGROUND)
+            || self.mask.contains(MaskReg::RENDER_SPRITES);
+
+        if !rendering || !(-1..=239).contains(&self.scanline) {
+            return;
+        }
+
+        // 8x16 sprites usually have their patterns at $1000
+        let sprites_high = self.control.contains(ControlReg::PATTERN_SPRITE)
+            || self.control.contains(ControlReg::SPRITE_SIZE);
+        let background_high = self.control.contains(ControlReg::PATTERN_BACKGROUND);
+
+        let rise = match (background_high, sprites_high) {
+            // sprite patterns are fetched from cycle 257 onwards
+            (false, true) => self.cycle == 260,
+            // background patterns for the next scanline are fetched from cycle 321 onwards
+            (true, false) => self.cycle == 324,
+            _ => false,
+        };
+
+        if rise {
+            if let Some(cart) = &self.cartridge {
+                cart.borrow_mut().ppu_a12_rise();
+            }
+        }
+    }
+
     pub fn insert_cartridge(&mut self, cartridge: Rc<RefCell<Cartridge>>) {
         self.cartridge = Some(cartridge)
     }
@@ -671,6 +755,20 @@ impl Ppu {
         data
     }
 
+    /// Same as `cpu_read`, but without side effects: the status and
+    /// the address are left untouched.
+    pub fn cpu_peek(&self, addr: u16) -> u8 {
+        use PPUReadWriteFlags::*;
+
+        match FromPrimitive::from_u16(addr & 0x07) {
+            Some(Status) => (self.status.bits() & 0xE0) | (self.ppu_data_buffer & 0x1F),
+            Some(OAMData) => self.oam.get_byte(self.oam_addr),
+            Some(PPUData) if self.vram_addr.0 >= 0x3F00 => self.ppu_read(self.vram_addr.0),
+            Some(PPUData) => self.ppu_data_buffer,
+            _ => 0,
+        }
+    }
+
     pub fn ppu_write(&mut self, addr: u16, data: u8) {
         let addr: u16 = addr & PPU_ADDR_END;
 
@@ -680,7 +778,7 @@ impl Ppu {
             .expect("No cartridge inserted!")
             .borrow_mut();
 
-        if let Some(_mapped_data) = cart.ppu_map_write(addr, data) {
+        if cart.ppu_map_write(addr, data) {
             return;
         }
 
@@ -688,37 +786,12 @@ impl Ppu {
             (0..=RAM_ADDR_END) => {
                 self.pattern_table[(addr as usize & 0x1000) >> 12][addr as usize & 0x0FFF] = data;
             }
-            (AFTER_RAM_END..=0x3EFF) => match cart.mirror {
-                CartridgeMirror::Vertical => match addr & 0x0FFF {
-                    (0x0000..=0x03FF) | (0x0800..=0x0BFF) => {
-                        self.name_table[0][addr as usize & 0x03FF] = data;
-                    }
-                    (0x0400..=0x07FF) | (0x0C00..=0x0FFF) => {
-                        self.name_table[1][addr as usize & 0x03FF] = data;
-                    }
-                    _ => { /* can't happen due to the mirror! */ }
-                },
-                CartridgeMirror::Horizontal => match addr & 0x0FFF {
-                    (0x0000..=0x07FF) => {
-                        self.name_table[0][addr as usize & 0x03FF] = data;
-                    }
-                    (0x0800..=0x0FFF) => {
-                        self.name_table[1][addr as usize & 0x03FF] = data;
-                    }
-                    _ => { /* can't happen due to the mirror! */ }
-                },
-                _ => {}
-            },
+            (AFTER_RAM_END..=0x3EFF) => {
+                let table = mirrored_name_table(addr, cart.mirror());
+                self.name_table[table][addr as usize & 0x03FF] = data;
+            }
             (0x3F00..=0x3FFF) => {
-                let addr = addr & 0x001F;
-                let addr = match addr {
-                    0x0010 => 0x0000,
-                    0x0014 => 0x0004,
-                    0x0018 => 0x0008,
-                    0x001C => 0x000C,
-                    _ => addr,
-                };
-                self.palette_table[addr as usize] = data;
+                self.palette_table[palette_index(addr)] = data;
             }
             _ => {}
         }
@@ -742,37 +815,12 @@ impl Ppu {
             (RAM_ADDR_START..=RAM_ADDR_END) => {
                 data = self.pattern_table[(addr as usize & 0x1000) >> 12][addr as usize & 0x0FFF];
             }
-            (AFTER_RAM_END..=0x3EFF) => match cart.mirror {
-                CartridgeMirror::Vertical => match addr & 0x0FFF {
-                    (0x0000..=0x03FF) | (0x0800..=0x0BFF) => {
-                        data = self.name_table[0][addr as usize & 0x03FF];
-                    }
-                    (0x0400..=0x07FF) | (0x0C00..=0x0FFF) => {
-                        data = self.name_table[1][addr as usize & 0x03FF];
-                    }
-                    _ => { /* can't happen due to the mirror! */ }
-                },
-                CartridgeMirror::Horizontal => match addr & 0x0FFF {
-                    (0x0000..=0x07FF) => {
-                        data = self.name_table[0][addr as usize & 0x03FF];
-                    }
-                    (0x0800..=0x0FFF) => {
-                        data = self.name_table[1][addr as usize & 0x03FF];
-                    }
-                    _ => { /* can't happen due to the mirror! */ }
-                },
-                _ => {}
-            },
+            (AFTER_RAM_END..=0x3EFF) => {
+                let table = mirrored_name_table(addr, cart.mirror());
+                data = self.name_table[table][addr as usize & 0x03FF];
+            }
             (0x3F00..=0x3FFF) => {
-                let addr = addr & 0x001F;
-                let addr = match addr {
-                    0x0010 => 0x0000,
-                    0x0014 => 0x0004,
-                    0x0018 => 0x0008,
-                    0x001C => 0x000C,
-                    _ => addr,
-                };
-                data = self.palette_table[addr as usize]
+                data = self.palette_table[palette_index(addr)]
                     & if self.mask.contains(MaskReg::GRAYSCALE) {
                         0x30
                     } else {
@@ -785,6 +833,15 @@ impl Ppu {
         data
     }
 
+    /// Same as `ppu_read`, but palette entries are returned as they
+    /// are stored, even in grayscale mode.
+    pub fn ppu_peek(&self, addr: u16) -> u8 {
+        match addr & PPU_ADDR_END {
+            (0x3F00..=0x3FFF) => self.palette_table[palette_index(addr)],
+            addr => self.ppu_read(addr),
+        }
+    }
+
     /// Returns a color from a palette and pixel index.
     pub fn color_from_palette(&self, palette_index: u8, pixel_index: u8) -> pixel::Pixel {
         // - 0x3F00 is the PPU offset where palettes are stored
@@ -794,6 +851,108 @@ impl Ppu {
         let index = self.ppu_read(0x3F00 + (palette_index as u16 * 4) + pixel_index as u16) & 0x3F;
         pixel::ALL_COLORS[index as usize]
     }
+
+    /// Writes the PPU's memory and registers to a save state.
+    ///
+    /// The screen is not saved, the next frame will overwrite it anyway.
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.frame_complete);
+
+        for table in &self.name_table {
+            state.write_bytes(table);
+        }
+        for table in &self.pattern_table {
+            state.write_bytes(table);
+        }
+        state.write_bytes(&self.palette_table);
+
+        state.write_bool(self.nmi);
+        state.write_i16(self.cycle);
+        state.write_i16(self.scanline);
+        state.write_bool(self.odd_frame);
+
+        self.oam.save_state(state);
+        state.write_u8(self.oam_addr);
+
+        state.write_u8(self.status.bits());
+        state.write_u8(self.mask.bits());
+        state.write_u8(self.control.bits());
+
+        state.write_u8(self.address_latch);
+        state.write_u8(self.ppu_data_buffer);
+
+        state.write_u16(self.vram_addr.0);
+        state.write_u16(self.tram_addr.0);
+        state.write_u8(self.fine_x);
+
+        self.bg.save_state(state);
+        self.fg.save_state(state);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.frame_complete = state.read_bool()?;
+
+        for table in self.name_table.iter_mut() {
+            state.read_bytes(table)?;
+        }
+        for table in self.pattern_table.iter_mut() {
+            state.read_bytes(table)?;
+        }
+        state.read_bytes(&mut self.palette_table)?;
+
+        self.nmi = state.read_bool()?;
+        self.cycle = state.read_i16()?;
+        self.scanline = state.read_i16()?;
+        self.odd_frame = state.read_bool()?;
+
+        self.oam.load_state(state)?;
+        self.oam_addr = state.read_u8()?;
+
+        self.status = StatusReg::from_bits_truncate(state.read_u8()?);
+        self.mask = MaskReg::from_bits_truncate(state.read_u8()?);
+        self.control = ControlReg::from_bits_truncate(state.read_u8()?);
+
+        self.address_latch = state.read_u8()?;
+        self.ppu_data_buffer = state.read_u8()?;
+
+        self.vram_addr = RamAddrData(state.read_u16()?);
+        self.tram_addr = RamAddrData(state.read_u16()?);
+        self.fine_x = state.read_u8()?;
+
+        self.bg.load_state(state)?;
+        self.fg.load_state(state)
+    }
+}
+
+/// Index in the palette table of a PPU address from $3F00 to $3FFF.
+///
+/// The background color of the sprite palettes mirrors the one of
+/// the background palettes.
+fn palette_index(addr: u16) -> usize {
+    match addr & 0x001F {
+        0x0010 => 0x0000,
+        0x0014 => 0x0004,
+        0x0018 => 0x0008,
+        0x001C => 0x000C,
+        addr => addr as usize,
+    }
+}
+
+/// Finds which of the PPU's two nametables is accessed by `addr`.
+///
+/// The PPU addresses four nametables, but only has memory for two of them.
+/// The cartridge decides how the four nametables are mirrored into the
+/// two physical ones.
+fn mirrored_name_table(addr: u16, mirror: CartridgeMirror) -> usize {
+    match mirror {
+        // $2000 = $2800 and $2400 = $2C00
+        CartridgeMirror::Vertical => (addr as usize >> 10) & 0x01,
+        // $2000 = $2400 and $2800 = $2C00
+        CartridgeMirror::Horizontal => (addr as usize >> 11) & 0x01,
+        // all four nametables are the same
+        CartridgeMirror::OneScreenLow => 0,
+        CartridgeMirror::OneScreenHigh => 1,
+    }
 }
 
 impl Default for Ppu {
@@ -1,5 +1,7 @@
 //! Module for the Object Attribute Memory (OAM).
 
+use crate::state::{StateError, StateReader, StateWriter};
+
 /// Object Address Memory. Stores sprites.
 #[derive(Clone, Copy, Debug)]
 pub struct Oam {
@@ -28,6 +30,19 @@ impl Oam {
         // mirror with 63 to avoid overflow
         self.mem[index as usize & 63] = entry.into();
     }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        for entry in &self.mem {
+            state.write_bytes(entry);
+        }
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        for entry in self.mem.iter_mut() {
+            state.read_bytes(entry)?;
+        }
+        Ok(())
+    }
 }
 
 impl Default for Oam {
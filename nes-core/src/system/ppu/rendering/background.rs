@@ -1,5 +1,7 @@
 //! Defines structures for rendering the background.
 
+use crate::state::{StateError, StateReader, StateWriter};
+
 /// Used to render the background.
 #[derive(Copy, Clone, Debug, Default)]
 pub(crate) struct BackgroundData {
@@ -12,3 +14,28 @@ pub(crate) struct BackgroundData {
     pub shifter_attrib_low: u16,
     pub shifter_attrib_high: u16,
 }
+
+impl BackgroundData {
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.next_tile_id);
+        state.write_u8(self.next_tile_attrib);
+        state.write_u8(self.next_tile_lsb);
+        state.write_u8(self.next_tile_msb);
+        state.write_u16(self.shifter_pattern_low);
+        state.write_u16(self.shifter_pattern_high);
+        state.write_u16(self.shifter_attrib_low);
+        state.write_u16(self.shifter_attrib_high);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.next_tile_id = state.read_u8()?;
+        self.next_tile_attrib = state.read_u8()?;
+        self.next_tile_lsb = state.read_u8()?;
+        self.next_tile_msb = state.read_u8()?;
+        self.shifter_pattern_low = state.read_u16()?;
+        self.shifter_pattern_high = state.read_u16()?;
+        self.shifter_attrib_low = state.read_u16()?;
+        self.shifter_attrib_high = state.read_u16()?;
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 //! Defines structures for rendering the foreground.
 
+use crate::state::{StateError, StateReader, StateWriter};
 use crate::system::ppu::oam::OamEntry;
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -12,3 +13,32 @@ pub(crate) struct ForegroundData {
     pub sprite_zero_hit_possible: bool,
     pub sprite_zero_being_rendered: bool,
 }
+
+impl ForegroundData {
+    pub fn save_state(&self, state: &mut StateWriter) {
+        for &sprite in &self.sprite_scanline {
+            state.write_bytes(&<[u8; 4]>::from(sprite));
+        }
+        state.write_u8(self.sprite_count);
+        state.write_bytes(&self.sprite_shifter_pattern_low);
+        state.write_bytes(&self.sprite_shifter_pattern_high);
+
+        state.write_bool(self.sprite_zero_hit_possible);
+        state.write_bool(self.sprite_zero_being_rendered);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        for sprite in self.sprite_scanline.iter_mut() {
+            let mut entry = [0; 4];
+            state.read_bytes(&mut entry)?;
+            *sprite = OamEntry::from(entry);
+        }
+        self.sprite_count = state.read_u8()?;
+        state.read_bytes(&mut self.sprite_shifter_pattern_low)?;
+        state.read_bytes(&mut self.sprite_shifter_pattern_high)?;
+
+        self.sprite_zero_hit_possible = state.read_bool()?;
+        self.sprite_zero_being_rendered = state.read_bool()?;
+        Ok(())
+    }
+}
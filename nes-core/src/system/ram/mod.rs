@@ -1,5 +1,7 @@
 //! Module for the RAM used by the 6502 CPU.
 
+use crate::state::{StateError, StateReader, StateWriter};
+
 /// Start of RAM
 pub const RAM_ADDR_START: u16 = 0x0000;
 
@@ -60,6 +62,14 @@ impl Ram {
     pub fn read_mirrored(&self, addr: u16, mirror: u16) -> u8 {
         self.mem[(addr & mirror) as usize]
     }
+
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_bytes(&self.mem);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.mem)
+    }
 }
 
 impl Default for Ram {
@@ -0,0 +1,333 @@
+//! CPU traces.
+//!
+//! A trace hook installed with `Nes::set_trace_hook` is called right
+//! before the CPU executes each instruction, with the state of the
+//! registers at that point. It's meant for debugging tools and for
+//! comparing the emulator against logs made by other emulators.
+//!
+//! A `Tracer` installed with `Nes::set_tracer` turns the same traces
+//! into a readable log, with one line per instruction.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use itertools::Itertools;
+
+use crate::disasm::{self, AddressingMode};
+use crate::system::bus::Bus;
+use crate::system::cpu::instructions::Instruction;
+
+/// Function called with the trace of every instruction.
+pub type TraceHook = Box<dyn FnMut(&CpuTrace)>;
+
+/// State of the console right before an instruction is executed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuTrace {
+    /// Address of the instruction
+    pub pc: u16,
+    /// Opcode followed by its operands. Only the first `len` bytes are used.
+    pub bytes: [u8; 3],
+    pub len: u8,
+
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    /// Status register
+    pub p: u8,
+    /// Stack pointer
+    pub sp: u8,
+
+    /// CPU cycles since the console was powered on
+    pub cycle: u64,
+    /// PPU scanline, from -1 (pre-render) to 260
+    pub scanline: i16,
+    /// PPU dot within the scanline
+    pub dot: i16,
+}
+
+impl CpuTrace {
+    /// Bytes of the instruction, opcode first.
+    pub fn instruction(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+/// Formats the trace like the logs of Nintendulator, without the
+/// disassembly:
+///
+/// `C000  4C F5 C5  A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7`
+impl fmt::Display for CpuTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self
+            .instruction()
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .join(" ");
+
+        write!(
+            f,
+            "{:04X}  {:<8}  A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            self.pc,
+            bytes,
+            self.a,
+            self.x,
+            self.y,
+            self.p,
+            self.sp,
+            self.scanline,
+            self.dot,
+            self.cycle
+        )
+    }
+}
+
+/// Where the lines of a `Tracer` go.
+enum TraceOutput {
+    Writer(Box<dyn Write>),
+    /// Only the last `capacity` lines are kept
+    RingBuffer {
+        lines: VecDeque<String>,
+        capacity: usize,
+    },
+}
+
+/// Logs every instruction executed by the CPU.
+///
+/// Lines follow the format of Nintendulator's logs, also used by
+/// nestest's log and understood by most trace comparison tools:
+///
+/// `C5F7  86 00     STX $00 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 36 CYC:12`
+///
+/// The disassembly shows the effective address of the instruction and
+/// the value found there before the instruction is executed. Unofficial
+/// opcodes are marked with a `*`.
+pub struct Tracer {
+    output: TraceOutput,
+    /// Only instructions in one of these ranges are logged, if not empty
+    ranges: Vec<RangeInclusive<u16>>,
+    /// First error returned by the writer, after which nothing is written
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    /// Creates a tracer that writes every line to `writer`.
+    ///
+    /// The writer is not buffered by the tracer. Wrap files in a
+    /// `BufWriter`.
+    pub fn new(writer: impl Write + 'static) -> Self {
+        Self::with_output(TraceOutput::Writer(Box::new(writer)))
+    }
+
+    /// Creates a tracer that only keeps the last `capacity` lines in
+    /// memory, e.g. to find out what led to a crash.
+    pub fn ring_buffer(capacity: usize) -> Self {
+        Self::with_output(TraceOutput::RingBuffer {
+            lines: VecDeque::new(),
+            capacity,
+        })
+    }
+
+    fn with_output(output: TraceOutput) -> Self {
+        Tracer {
+            output,
+            ranges: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Only logs instructions located in `range`. Can be called multiple
+    /// times to log instructions from multiple ranges.
+    pub fn with_range(mut self, range: RangeInclusive<u16>) -> Self {
+        self.ranges.push(range);
+        self
+    }
+
+    /// Lines kept by a ring buffer tracer, oldest first.
+    /// Always empty for tracers made with `Tracer::new`.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        let lines = match &self.output {
+            TraceOutput::RingBuffer { lines, .. } => Some(lines.iter().map(String::as_str)),
+            TraceOutput::Writer(_) => None,
+        };
+        lines.into_iter().flatten()
+    }
+
+    /// Writes the lines kept by a ring buffer tracer to `writer`.
+    pub fn write_lines(&self, writer: &mut impl Write) -> io::Result<()> {
+        for line in self.lines() {
+            writeln!(writer, "{line}")?;
+        }
+        Ok(())
+    }
+
+    /// The error returned by the writer, if any. The tracer stops
+    /// writing after the first error.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Flushes the writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.output {
+            TraceOutput::Writer(writer) => writer.flush(),
+            TraceOutput::RingBuffer { .. } => Ok(()),
+        }
+    }
+
+    /// Logs the instruction described by `trace`, which is about to be
+    /// executed. `bus` is used to show the operands' values.
+    pub(crate) fn trace(&mut self, trace: &CpuTrace, bus: &Bus) {
+        if self.error.is_some() {
+            return;
+        }
+        if !self.ranges.is_empty() && !self.ranges.iter().any(|r| r.contains(&trace.pc)) {
+            return;
+        }
+
+        let line = trace_line(trace, bus);
+        match &mut self.output {
+            TraceOutput::Writer(writer) => {
+                if let Err(error) = writeln!(writer, "{line}") {
+                    self.error = Some(error);
+                }
+            }
+            TraceOutput::RingBuffer { lines, capacity } => {
+                if lines.len() >= *capacity {
+                    lines.pop_front();
+                }
+                if *capacity > 0 {
+                    lines.push_back(line);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let output = match &self.output {
+            TraceOutput::Writer(_) => "Writer",
+            TraceOutput::RingBuffer { .. } => "RingBuffer",
+        };
+        f.debug_struct("Tracer")
+            .field("output", &output)
+            .field("ranges", &self.ranges)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+/// Formats a trace along with its disassembly.
+fn trace_line(trace: &CpuTrace, bus: &Bus) -> String {
+    let instruction = Instruction::lookup(trace.bytes[0]);
+    let unofficial = if instruction.official() { ' ' } else { '*' };
+
+    // the disassembly goes between the bytes and the registers
+    let line = trace.to_string();
+    let (bytes, registers) = line.split_at(15);
+    format!(
+        "{bytes}{unofficial}{:<32}{}",
+        disassemble(trace, bus),
+        registers.trim_start()
+    )
+}
+
+/// Disassembles the traced instruction, resolving its effective address.
+fn disassemble(trace: &CpuTrace, bus: &Bus) -> String {
+    use AddressingMode::*;
+
+    let Some(instruction) = disasm::decode(trace.instruction(), trace.pc) else {
+        return String::new();
+    };
+    let mnemonic = instruction.mnemonic;
+    let operand = instruction.operand.unwrap_or(0);
+    let low = operand as u8;
+
+    // reads a 16-bit pointer from the zero page
+    let zp_pointer = |addr: u8| {
+        u16::from_le_bytes([bus.peek(addr as u16), bus.peek(addr.wrapping_add(1) as u16)])
+    };
+
+    let operand = match instruction.mode {
+        Implied => String::new(),
+        Accumulator => "A".to_string(),
+        Immediate => format!("#${low:02X}"),
+        ZeroPage => format!("${low:02X} = {:02X}", bus.peek(low as u16)),
+        ZeroPageX | ZeroPageY => {
+            let (index, name) = match instruction.mode {
+                ZeroPageX => (trace.x, 'X'),
+                _ => (trace.y, 'Y'),
+            };
+            let addr = low.wrapping_add(index);
+            format!(
+                "${low:02X},{name} @ {addr:02X} = {:02X}",
+                bus.peek(addr as u16)
+            )
+        }
+        Absolute => match mnemonic {
+            "JMP" | "JSR" => format!("${operand:04X}"),
+            _ => format!("${operand:04X} = {:02X}", bus.peek(operand)),
+        },
+        AbsoluteX | AbsoluteY => {
+            let (index, name) = match instruction.mode {
+                AbsoluteX => (trace.x, 'X'),
+                _ => (trace.y, 'Y'),
+            };
+            let addr = operand.wrapping_add(index as u16);
+            format!(
+                "${operand:04X},{name} @ {addr:04X} = {:02X}",
+                bus.peek(addr)
+            )
+        }
+        Indirect => {
+            // the high byte is read without crossing the page
+            let high_addr = (operand & 0xFF00) | (operand.wrapping_add(1) & 0x00FF);
+            let addr = u16::from_le_bytes([bus.peek(operand), bus.peek(high_addr)]);
+            format!("(${operand:04X}) = {addr:04X}")
+        }
+        IndirectX => {
+            let pointer = low.wrapping_add(trace.x);
+            let addr = zp_pointer(pointer);
+            format!(
+                "(${low:02X},X) @ {pointer:02X} = {addr:04X} = {:02X}",
+                bus.peek(addr)
+            )
+        }
+        IndirectY => {
+            let base = zp_pointer(low);
+            let addr = base.wrapping_add(trace.y as u16);
+            format!(
+                "(${low:02X}),Y = {base:04X} @ {addr:04X} = {:02X}",
+                bus.peek(addr)
+            )
+        }
+        Relative => format!("${:04X}", instruction.target.unwrap_or_default()),
+    };
+
+    if operand.is_empty() {
+        mnemonic.to_string()
+    } else {
+        format!("{mnemonic} {operand}")
+    }
+}
+
+#[test]
+fn test_tracer() {
+    use crate::{cartridge::Cartridge, Nes};
+
+    let mut nes = Nes::new(Cartridge::from_file("test_data/roms/nestest.nes").unwrap());
+    nes.set_tracer(Tracer::ring_buffer(2).with_range(0xC004..=0xC006));
+    nes.next_frame();
+
+    // only the last two instructions in the range are kept
+    let tracer = nes.take_tracer().unwrap();
+    assert_eq!(
+        tracer.lines().collect::<Vec<_>>(),
+        [
+            "C005  D8        CLD                             A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 27 CYC:9",
+            "C006  A2 FF     LDX #$FF                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 33 CYC:11",
+        ]
+    );
+}
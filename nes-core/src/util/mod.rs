@@ -0,0 +1,169 @@
+//! Helpers shared by the rest of the crate.
+
+/// Lookup table for the CRC-32 used by zlib, PNG and ROM databases.
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// CRC-32 (ISO-HDLC) checksum of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Amount of bits each word is rotated by, for every step of MD5.
+const MD5_SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+/// MD5 digest of `data`, used by FCEUX to identify ROMs.
+pub fn md5(data: &[u8]) -> [u8; 16] {
+    // constants from the integer part of the sines of 1 to 64
+    let constants: Vec<u32> = (1..=64)
+        .map(|i| ((i as f64).sin().abs() * 4_294_967_296.0) as u32)
+        .collect();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_le_bytes());
+
+    let mut state = [0x6745_2301u32, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476];
+    for block in message.chunks_exact(64) {
+        let words: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f
+                .wrapping_add(a)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(MD5_SHIFTS[i]));
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 16];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 encoding of `data`, with padding.
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (bits >> (18 - i * 6)) & 0x3F;
+                text.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Decodes standard base64, with or without padding. Returns `None` if
+/// `text` isn't valid base64.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=').as_bytes();
+    if text.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.chunks(4) {
+        let mut bits = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = BASE64_ALPHABET.iter().position(|&a| a == c)? as u32;
+            bits |= value << (18 - i * 6);
+        }
+        data.extend_from_slice(&bits.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(data)
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+}
+
+#[test]
+fn test_md5() {
+    let hex = |digest: [u8; 16]| {
+        digest
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>()
+    };
+    assert_eq!(hex(md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+    assert_eq!(
+        hex(md5(b"The quick brown fox jumps over the lazy dog")),
+        "9e107d9d372bb6826bd81d3542a419d6"
+    );
+    assert_eq!(hex(md5(&[0x61; 100])), "36a92cc94a9e0fa21f625f8bfb007adf");
+}
+
+#[test]
+fn test_base64() {
+    assert_eq!(base64_encode(b""), "");
+    assert_eq!(base64_encode(b"Ma"), "TWE=");
+    assert_eq!(base64_encode(b"Many"), "TWFueQ==");
+    assert_eq!(base64_decode("TWFueQ==").unwrap(), b"Many");
+    assert_eq!(base64_decode("TWFu").unwrap(), b"Man");
+    assert_eq!(base64_decode("TW!u"), None);
+}
@@ -1,5 +1,7 @@
 #![cfg(not(target_arch = "wasm32"))]
 
+use std::path::{Path, PathBuf};
+
 use instant::Duration;
 
 pub fn prepare_env() {
@@ -19,3 +21,37 @@ pub fn sleep(duration: Duration) {
 pub fn spawn<F: std::future::Future + 'static>(fut: F) {
     pollster::block_on(fut);
 }
+
+/// Saves `data` in the working directory as `name`, adding a number to the
+/// name if a file already has it.
+pub fn save_file(name: &str, data: &[u8]) {
+    let path = unused_path(name);
+    match std::fs::write(&path, data) {
+        Ok(()) => log::info!("saved {}", path.display()),
+        Err(err) => log::error!("couldn't save {}: {err}", path.display()),
+    }
+}
+
+/// Creates a file in the working directory, named like `save_file` does,
+/// to be written while the game runs.
+pub fn create_file(name: &str) -> std::io::Result<std::fs::File> {
+    let path = unused_path(name);
+    log::info!("writing {}", path.display());
+    std::fs::File::create(path)
+}
+
+fn unused_path(name: &str) -> PathBuf {
+    let path = Path::new(name);
+    let (stem, extension) = (
+        path.file_stem().unwrap_or_default().to_string_lossy(),
+        path.extension().unwrap_or_default().to_string_lossy(),
+    );
+
+    (0..)
+        .map(|i| match i {
+            0 => path.to_path_buf(),
+            i => format!("{stem}-{i}.{extension}").into(),
+        })
+        .find(|path: &PathBuf| !path.exists())
+        .unwrap()
+}
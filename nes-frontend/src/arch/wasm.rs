@@ -83,3 +83,38 @@ pub fn sleep(duration: Duration) {
 pub fn spawn<F: std::future::Future<Output = ()> + 'static>(fut: F) {
     wasm_bindgen_futures::spawn_local(fut)
 }
+
+/// Downloads `data` as a file called `name`.
+pub fn save_file(name: &str, data: &[u8]) {
+    use wasm_bindgen::JsCast;
+
+    let download = || -> Result<(), wasm_bindgen::JsValue> {
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&parts)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+        let link: web_sys::HtmlAnchorElement = web_sys::window()
+            .and_then(|win| win.document())
+            .ok_or("no document")?
+            .create_element("a")?
+            .dyn_into()?;
+        link.set_href(&url);
+        link.set_download(name);
+        link.click();
+
+        web_sys::Url::revoke_object_url(&url)
+    };
+
+    if let Err(err) = download() {
+        log::error!("couldn't save {name}: {err:?}");
+    }
+}
+
+/// Files can't be written while the game runs on the web, they can only
+/// be downloaded once complete.
+pub fn create_file(_name: &str) -> std::io::Result<std::fs::File> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "files can't be written on the web",
+    ))
+}
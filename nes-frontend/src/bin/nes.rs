@@ -20,8 +20,9 @@ use nes_frontend::game::GameState;
 
 const NES_SIZE: LogicalSize<u32> = LogicalSize::new(NES_WIDTH as u32, NES_HEIGHT as u32);
 const SCALED_SIZE: LogicalSize<u32> = LogicalSize::new(NES_SIZE.width * 3, NES_SIZE.height * 3);
+/// Updates per second until a game is started, which then sets its
+/// own frame rate
 const FPS: u32 = 60;
-const FRAME_TIME: Duration = Duration::from_micros(1_000_000 / FPS as u64);
 
 fn main() {
     arch::prepare_env();
@@ -43,14 +44,21 @@ async fn run() {
         0.1,
         |g| {
             // Update function
+            // PAL games run at 50 frames per second
+            let fps = g.game.frame_rate().round() as u32;
+            if g.updates_per_second != fps {
+                g.set_updates_per_second(fps);
+            }
+
             g.game.update();
             g.game.draw();
             g.window.request_redraw();
         },
         move |g| {
             // Render function
-            if time.elapsed() < FRAME_TIME {
-                arch::sleep(FRAME_TIME.saturating_sub(time.elapsed()));
+            let frame_time = Duration::from_secs_f64(1.0 / g.game.frame_rate());
+            if time.elapsed() < frame_time {
+                arch::sleep(frame_time.saturating_sub(time.elapsed()));
             }
 
             time = Instant::now();
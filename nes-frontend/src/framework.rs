@@ -58,6 +58,11 @@ impl Framework {
         let _ = self.egui_state.on_event(&self.egui_ctx, event);
     }
 
+    /// Whether the mouse is over the GUI, or dragging one of its widgets.
+    pub fn wants_pointer_input(&self) -> bool {
+        self.egui_ctx.wants_pointer_input()
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
         if width > 0 && height > 0 {
             self.screen_descriptor.size_in_pixels = [width, height];
@@ -1,6 +1,10 @@
 use fnv::FnvHashMap;
 use nes_core::cartridge::{Cartridge, CartridgeError};
-use nes_core::controller::Controller;
+use nes_core::controller::{Controller, Port, StandardController, Zapper};
+use nes_core::recorder::Recorder;
+use nes_core::region::Region;
+use nes_core::rewind::Rewind;
+use nes_core::screen::ImageOptions;
 use nes_core::Nes;
 use pixels::Pixels;
 use tokio::sync::mpsc::Receiver;
@@ -12,6 +16,11 @@ use crate::gui::GuiEvent;
 
 pub struct GameState {
     nes: Option<Nes>,
+    rewind: Rewind,
+    /// Region chosen by the user, replacing the one of the cartridge
+    region: Option<Region>,
+    /// Whether a Zapper replaces the second controller, aimed with the mouse
+    zapper: bool,
     pub input: WinitInputHelper,
     pub input_map: FnvHashMap<VirtualKeyCode, Controller>,
     pub pixels: Pixels,
@@ -29,6 +38,9 @@ impl GameState {
     ) -> Self {
         GameState {
             nes: None,
+            rewind: Rewind::default(),
+            region: None,
+            zapper: false,
             input,
             pixels,
             framework,
@@ -50,29 +62,111 @@ impl GameState {
     }
 
     pub fn start_from_file(&mut self, file_name: Option<&str>) -> Result<(), CartridgeError> {
-        self.nes = match file_name {
-            Some(file_name) => Some(Nes::new(Cartridge::from_file(file_name)?)),
+        let cart = match file_name {
+            Some(file_name) => Some(Cartridge::from_file(file_name)?),
             None => None,
         };
+        self.start_from_cartridge(cart);
         Ok(())
     }
 
     pub fn start_from_bytes(&mut self, bytes: Option<&[u8]>) -> Result<(), CartridgeError> {
-        self.nes = match bytes {
-            Some(bytes) => Some(Nes::new(Cartridge::from_bytes(bytes)?)),
+        let cart = match bytes {
+            Some(bytes) => Some(Cartridge::from_bytes(bytes)?),
             None => None,
         };
+        self.start_from_cartridge(cart);
         Ok(())
     }
 
     pub fn start_from_cartridge(&mut self, cart: Option<Cartridge>) {
+        self.stop_recording();
         self.nes = cart.map(Nes::new);
+        if let (Some(nes), Some(region)) = (self.nes.as_mut(), self.region) {
+            nes.set_region(region);
+        }
+        self.set_zapper(self.zapper);
+        self.rewind.clear();
+    }
+
+    /// Frames per second of the running game, 60 if no game is running.
+    pub fn frame_rate(&self) -> f64 {
+        self.nes.as_ref().map_or(60.0, Nes::frame_rate)
+    }
+
+    /// Changes the region of the running game and of the next ones.
+    /// `None` goes back to the region of the cartridge. Older snapshots
+    /// would bring back the previous region, so the rewind buffer is
+    /// cleared.
+    pub fn set_region(&mut self, region: Option<Region>) {
+        self.region = region;
+        self.rewind.clear();
+        if let Some(nes) = self.nes.as_mut() {
+            let region =
+                region.unwrap_or_else(|| Region::from_cartridge(nes.cartridge_metadata().region));
+            nes.set_region(region);
+        }
+    }
+
+    /// Plugs a Zapper into the second controller port, or plugs the
+    /// controller back. Older snapshots can't be loaded with the other
+    /// device, so the rewind buffer is cleared.
+    pub fn set_zapper(&mut self, zapper: bool) {
+        self.zapper = zapper;
+        self.rewind.clear();
+        if let Some(nes) = self.nes.as_mut() {
+            if zapper {
+                nes.set_input_device(Port::Two, Some(Box::new(Zapper::new())));
+            } else {
+                nes.set_input_device(Port::Two, Some(Box::new(StandardController::new())));
+            }
+        }
     }
 
     pub fn restart(&mut self) {
         self.nes.as_mut().map(Nes::system_reset);
     }
 
+    /// Saves the current frame as a PNG image.
+    pub fn save_screenshot(&self) {
+        if let Some(nes) = self.nes.as_ref() {
+            let mut png = Vec::new();
+            // writing to a `Vec` can't fail
+            nes.screen()
+                .write_png(&mut png, ImageOptions::new())
+                .unwrap();
+            crate::arch::save_file("screenshot.png", &png);
+        }
+    }
+
+    /// Starts recording the game to an AVI video, or stops the recording.
+    pub fn toggle_recording(&mut self) {
+        let Some(nes) = self.nes.as_mut() else {
+            return;
+        };
+        if nes.recorder().is_some() {
+            self.stop_recording();
+            return;
+        }
+
+        match crate::arch::create_file("recording.avi") {
+            Ok(file) => {
+                nes.set_recorder(Recorder::avi(std::io::BufWriter::new(file)));
+            }
+            Err(err) => log::error!("couldn't start recording: {err}"),
+        }
+    }
+
+    pub fn stop_recording(&mut self) {
+        if let Some(recorder) = self.nes.as_mut().and_then(Nes::take_recorder) {
+            let frames = recorder.frames();
+            match recorder.finish() {
+                Ok(()) => log::info!("recorded {frames} frames"),
+                Err(err) => log::error!("couldn't record the video: {err}"),
+            }
+        }
+    }
+
     pub fn draw(&mut self) {
         if let Some(nes) = self.nes.as_ref() {
             self.pixels
@@ -89,16 +183,37 @@ impl GameState {
         self.treat_gui_events();
         self.update_controllers();
         if let Some(nes) = self.nes.as_mut() {
-            nes.next_frame();
+            // holding R runs the game backwards
+            if self.input.key_held(VirtualKeyCode::R) {
+                self.rewind.step_back(nes);
+            } else {
+                nes.next_frame();
+                self.rewind.push(nes);
+            }
         }
     }
 
     pub fn update_controllers(&mut self) {
-        let [controller1, controller2] = match self.nes.as_mut() {
-            Some(nes) => nes.mut_controllers(),
-            None => return,
+        let Some(nes) = self.nes.as_mut() else {
+            return;
         };
 
+        if self.zapper {
+            // the mouse is ignored while it's over the GUI
+            let over_gui = self.framework.wants_pointer_input();
+            let aim = self
+                .input
+                .mouse()
+                .filter(|_| !over_gui)
+                .and_then(|position| self.pixels.window_pos_to_pixel(position).ok());
+            let trigger = !over_gui && self.input.mouse_held(0);
+            if let Some(zapper) = nes.mut_input_device(Port::Two) {
+                zapper.set_aim(aim, trigger);
+            }
+        }
+
+        let [controller1, controller2] = nes.mut_controllers();
+
         *controller1 = Controller::empty();
         *controller2 = Controller::empty();
 
@@ -117,6 +232,10 @@ impl GameState {
                     self.start_from_cartridge(Some(cart));
                 }
                 GuiEvent::ChangeRom(None) => self.start_from_cartridge(None),
+                GuiEvent::ChangeRegion(region) => self.set_region(region),
+                GuiEvent::SetZapper(zapper) => self.set_zapper(zapper),
+                GuiEvent::SaveScreenshot => self.save_screenshot(),
+                GuiEvent::ToggleRecording => self.toggle_recording(),
                 GuiEvent::ToggleSettings => self.framework.gui.settings_window.toggle(),
                 GuiEvent::CartridgeError(message) => {
                     self.framework.gui.settings_window.toggle();
@@ -154,5 +273,15 @@ impl GameState {
         if self.input.key_pressed(VirtualKeyCode::F5) {
             self.restart();
         }
+
+        // Start or stop recording
+        if self.input.key_pressed(VirtualKeyCode::F9) {
+            self.toggle_recording();
+        }
+
+        // Save screenshot
+        if self.input.key_pressed(VirtualKeyCode::F12) {
+            self.save_screenshot();
+        }
     }
 }
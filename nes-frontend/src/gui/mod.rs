@@ -3,6 +3,7 @@ mod settings;
 
 use egui::Context;
 use nes_core::cartridge::Cartridge;
+use nes_core::region::Region;
 use tokio::sync::mpsc::Sender;
 
 use self::error::ErrorWindow;
@@ -11,6 +12,12 @@ use self::settings::SettingsWindow;
 #[derive(Debug)]
 pub enum GuiEvent {
     ChangeRom(Option<(String, Cartridge)>),
+    /// `None` uses the region of the cartridge
+    ChangeRegion(Option<Region>),
+    /// Plugs a Zapper into the second port, aimed with the mouse
+    SetZapper(bool),
+    SaveScreenshot,
+    ToggleRecording,
     ToggleSettings,
     CartridgeError(String),
 }
@@ -5,6 +5,7 @@ use rfd::AsyncFileDialog;
 use tokio::sync::mpsc::Sender;
 
 use nes_core::cartridge::Cartridge;
+use nes_core::region::Region;
 
 use super::GuiEvent;
 
@@ -14,6 +15,9 @@ static PRELOADED_ROMS: Dir = include_dir!("$CARGO_MANIFEST_DIR/../roms");
 pub struct SettingsWindow {
     pub open: bool,
     pub selected_cart_name: Option<String>,
+    /// `None` uses the region of the cartridge
+    region: Option<Region>,
+    zapper: bool,
     cartridges: FnvHashMap<String, Cartridge>,
 
     event_sender: Sender<GuiEvent>,
@@ -24,6 +28,8 @@ impl SettingsWindow {
         Self {
             open: true,
             selected_cart_name: None,
+            region: None,
+            zapper: false,
             cartridges: prepare_carts(),
             event_sender,
         }
@@ -135,6 +141,68 @@ impl SettingsWindow {
             }
             ui.label("Load ROM from storage");
         });
+
+        // Button to save the current frame
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                crate::event!(self.event_sender, |sender| {
+                    sender.send(GuiEvent::SaveScreenshot).await.unwrap();
+                });
+            }
+            ui.label("Save a screenshot (F12)");
+        });
+
+        // Button to start and stop recording a video
+        ui.horizontal(|ui| {
+            if ui.button("Record").clicked() {
+                crate::event!(self.event_sender, |sender| {
+                    sender.send(GuiEvent::ToggleRecording).await.unwrap();
+                });
+            }
+            ui.label("Start or stop recording a video (F9)");
+        });
+
+        // TV system, changes the speed of the game
+        let curr_region = self.region;
+        let region_name = |region: Option<Region>| match region {
+            None => "Auto",
+            Some(Region::Ntsc) => "NTSC",
+            Some(Region::Pal) => "PAL",
+            Some(Region::Dendy) => "Dendy",
+        };
+        egui::ComboBox::from_label("Region")
+            .selected_text(region_name(self.region))
+            .show_ui(ui, |ui| {
+                for region in [
+                    None,
+                    Some(Region::Ntsc),
+                    Some(Region::Pal),
+                    Some(Region::Dendy),
+                ] {
+                    ui.selectable_value(&mut self.region, region, region_name(region));
+                }
+            });
+
+        if curr_region != self.region {
+            let region = self.region;
+            crate::event!(self.event_sender, |sender| {
+                sender.send(GuiEvent::ChangeRegion(region)).await.unwrap();
+            });
+        }
+
+        // Light gun on the second port, e.g. for Duck Hunt
+        if ui
+            .checkbox(
+                &mut self.zapper,
+                "Zapper on port 2 (aim and shoot with the mouse)",
+            )
+            .changed()
+        {
+            let zapper = self.zapper;
+            crate::event!(self.event_sender, |sender| {
+                sender.send(GuiEvent::SetZapper(zapper)).await.unwrap();
+            });
+        }
     }
 
     fn ui_settings(&mut self, ui: &mut Ui) {
@@ -0,0 +1,288 @@
+//! Runs a game without a display, for scripted runs and CI machines.
+//!
+//! The game runs for a number of frames, or until a byte of memory
+//! holds a value, while an input script presses the buttons. The last
+//! frame and the RAM can then be saved, and compared against expected
+//! values.
+
+mod script;
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process::ExitCode;
+
+use nes_core::cartridge::Cartridge;
+use nes_core::controller::Controller;
+use nes_core::movie::Movie;
+use nes_core::recorder::Recorder;
+use nes_core::region::Region;
+use nes_core::screen::{ImageOptions, NesScreen};
+use nes_core::Nes;
+
+use script::InputScript;
+
+const USAGE: &str = "\
+Usage: nes-headless <ROM> [OPTIONS]
+
+Options:
+  --frames <N>             Frames to run, or the most frames to run with --until [default: 60]
+  --input <FILE>           Input script, see below
+  --movie <FILE>           FM2 movie to play back instead of an input script
+  --region <REGION>        ntsc, pal or dendy [default: from the ROM's header]
+  --until <ADDR>=<VALUE>   Stops once the byte at ADDR holds VALUE
+  --screenshot <FILE>      Writes the last frame as a PNG image, or a PPM
+                           image if FILE ends with .ppm
+  --ram-dump <FILE>        Writes the 2 KB of RAM of the console
+  --record <FILE>          Records a video of the run, as Y4M without audio if
+                           FILE ends with .y4m, as AVI with audio otherwise
+  --record-start <N>       Frame the recording starts at [default: 0]
+  --record-stop <N>        Frame the recording stops at, after --record-start
+                           [default: the end of the run]
+  --expect <ADDR>=<VALUE>  Fails unless the byte at ADDR holds VALUE at the end.
+                           Can be repeated.
+  --expect-hash <CRC32>    Fails unless the CRC-32 of the last frame is CRC32
+  -h, --help               Prints this message
+
+Addresses and values are decimal, or hexadecimal when prefixed by $ or 0x.
+
+Input scripts hold one change of the controllers per line:
+  <FRAME> <CONTROLLER> [BUTTON]...
+The buttons (A, B, SELECT, START, UP, DOWN, LEFT, RIGHT) of controller 1 or 2
+are held from FRAME, counted from 0, until the next line for the same
+controller. Lines starting with # are ignored.
+
+Exit status: 0 if every expectation is met, 1 if one isn't, 2 on errors.";
+
+/// Size of the console's RAM, mirrored up to $1FFF.
+const RAM_SIZE: u16 = 0x0800;
+
+#[derive(Debug)]
+struct Options {
+    rom: String,
+    frames: u32,
+    input: Option<String>,
+    movie: Option<String>,
+    region: Option<Region>,
+    until: Option<(u16, u8)>,
+    screenshot: Option<String>,
+    ram_dump: Option<String>,
+    record: Option<String>,
+    record_start: u32,
+    record_stop: Option<u32>,
+    expect: Vec<(u16, u8)>,
+    expect_hash: Option<u32>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut rom = None;
+        let mut options = Options {
+            rom: String::new(),
+            frames: 60,
+            input: None,
+            movie: None,
+            region: None,
+            until: None,
+            screenshot: None,
+            ram_dump: None,
+            record: None,
+            record_start: 0,
+            record_stop: None,
+            expect: Vec::new(),
+            expect_hash: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+
+            match arg.as_str() {
+                "--frames" => options.frames = value()?.parse()?,
+                "--input" => options.input = Some(value()?),
+                "--movie" => options.movie = Some(value()?),
+                "--region" => {
+                    options.region = Some(match value()?.to_ascii_lowercase().as_str() {
+                        "ntsc" => Region::Ntsc,
+                        "pal" => Region::Pal,
+                        "dendy" => Region::Dendy,
+                        region => return Err(format!("unknown region {region}").into()),
+                    })
+                }
+                "--until" => options.until = Some(parse_condition(&value()?)?),
+                "--screenshot" => options.screenshot = Some(value()?),
+                "--ram-dump" => options.ram_dump = Some(value()?),
+                "--record" => options.record = Some(value()?),
+                "--record-start" => options.record_start = value()?.parse()?,
+                "--record-stop" => options.record_stop = Some(value()?.parse()?),
+                "--expect" => options.expect.push(parse_condition(&value()?)?),
+                "--expect-hash" => {
+                    let hash = value()?;
+                    let hash = hash.trim_start_matches("0x").trim_start_matches('$');
+                    options.expect_hash = Some(u32::from_str_radix(hash, 16)?);
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown option {arg}").into()),
+                _ if rom.is_none() => rom = Some(arg),
+                _ => return Err(format!("unexpected argument {arg}").into()),
+            }
+        }
+
+        options.rom = rom.ok_or("missing the ROM")?;
+        if options
+            .record_stop
+            .is_some_and(|stop| stop <= options.record_start)
+        {
+            return Err("--record-stop must come after --record-start".into());
+        }
+        Ok(options)
+    }
+}
+
+/// Parses `<ADDR>=<VALUE>`.
+fn parse_condition(condition: &str) -> Result<(u16, u8), Box<dyn Error>> {
+    let (addr, value) = condition
+        .split_once('=')
+        .ok_or_else(|| format!("expected <ADDR>=<VALUE>, found {condition}"))?;
+    Ok((parse_number(addr)?, parse_number(value)?))
+}
+
+fn parse_number<T: TryFrom<u32>>(text: &str) -> Result<T, Box<dyn Error>> {
+    let number = match text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+        Some(hex) => u32::from_str_radix(hex, 16)?,
+        None => text.parse()?,
+    };
+    T::try_from(number).map_err(|_| format!("{text} is out of range").into())
+}
+
+/// Writes the screen as a PNG image, or as a binary PPM image if the
+/// path ends with `.ppm`.
+fn write_screenshot(screen: &NesScreen, path: &str) -> Result<(), Box<dyn Error>> {
+    let file = BufWriter::new(File::create(path)?);
+    if path.to_ascii_lowercase().ends_with(".ppm") {
+        screen.write_ppm(file, ImageOptions::new())?;
+    } else {
+        screen.write_png(file, ImageOptions::new())?;
+    }
+    Ok(())
+}
+
+/// Creates a Y4M recorder if the path ends with `.y4m`, an AVI recorder
+/// otherwise.
+fn create_recorder(path: &str) -> Result<Recorder, Box<dyn Error>> {
+    let file = BufWriter::new(File::create(path)?);
+    if path.to_ascii_lowercase().ends_with(".y4m") {
+        Ok(Recorder::y4m(file))
+    } else {
+        Ok(Recorder::avi(file))
+    }
+}
+
+/// Runs the game, returning true if every expectation is met.
+fn run(options: &Options) -> Result<bool, Box<dyn Error>> {
+    let mut nes = Nes::new(Cartridge::from_file(&options.rom)?);
+    if let Some(region) = options.region {
+        nes.set_region(region);
+    }
+    let script = match &options.input {
+        Some(path) => InputScript::parse(&fs::read_to_string(path)?)?,
+        None => InputScript::default(),
+    };
+    let movie = match &options.movie {
+        Some(path) => {
+            let movie = Movie::from_fm2(&fs::read_to_string(path)?)?;
+            movie.restart(&mut nes)?;
+            Some(movie)
+        }
+        None => None,
+    };
+
+    let mut controllers = [Controller::empty(); 2];
+    let mut frames = 0;
+    let mut reached = false;
+
+    while frames < options.frames && !reached {
+        if let Some(path) = &options.record {
+            if frames == options.record_start {
+                nes.set_recorder(create_recorder(path)?);
+            }
+        }
+        if options.record_stop == Some(frames) {
+            if let Some(recorder) = nes.take_recorder() {
+                recorder.finish()?;
+            }
+        }
+
+        script.apply(frames, &mut controllers);
+        let playing = movie
+            .as_ref()
+            .is_some_and(|movie| movie.play(frames as usize, &mut nes));
+        if !playing {
+            *nes.mut_controllers() = controllers;
+        }
+        nes.next_frame();
+        frames += 1;
+
+        reached = options
+            .until
+            .is_some_and(|(addr, value)| nes.peek(addr) == value);
+    }
+
+    if let Some(recorder) = nes.take_recorder() {
+        println!("recorded frames: {}", recorder.frames());
+        recorder.finish()?;
+    }
+
+    let hash = nes.screen().crc32();
+    println!("frames: {frames}");
+    println!("frame hash: {hash:08X}");
+
+    if let Some(path) = &options.screenshot {
+        write_screenshot(nes.screen(), path)?;
+    }
+    if let Some(path) = &options.ram_dump {
+        let ram: Vec<u8> = (0..RAM_SIZE).map(|addr| nes.peek(addr)).collect();
+        fs::write(path, ram)?;
+    }
+
+    let mut success = true;
+    if let (Some((addr, value)), false) = (options.until, reached) {
+        println!("${addr:04X} never held ${value:02X}");
+        success = false;
+    }
+    for &(addr, value) in &options.expect {
+        let found = nes.peek(addr);
+        if found != value {
+            println!("${addr:04X}: expected ${value:02X}, found ${found:02X}");
+            success = false;
+        }
+    }
+    if let Some(expected) = options.expect_hash {
+        if hash != expected {
+            println!("frame hash: expected {expected:08X}, found {hash:08X}");
+            success = false;
+        }
+    }
+
+    Ok(success)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return if args.is_empty() {
+            ExitCode::from(2)
+        } else {
+            ExitCode::SUCCESS
+        };
+    }
+
+    let result = Options::parse(args.into_iter()).and_then(|options| run(&options));
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Input scripts, which press the controllers' buttons on given frames.
+//!
+//! Each line of a script changes the buttons held on one controller:
+//!
+//! ```text
+//! # frame controller buttons
+//! 30 1 START
+//! 31 1
+//! 90 1 A RIGHT
+//! ```
+//!
+//! The buttons are held from the given frame, counted from 0, until the
+//! next line for the same controller. A line without buttons releases
+//! every button.
+
+use nes_core::controller::Controller;
+
+/// Buttons held on a controller from a frame on.
+#[derive(Clone, Copy, Debug)]
+struct Change {
+    frame: u32,
+    /// 0 or 1
+    controller: usize,
+    buttons: Controller,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InputScript {
+    /// Sorted by frame
+    changes: Vec<Change>,
+}
+
+impl InputScript {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut changes = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {message}", i + 1);
+
+            let mut words = line.split_whitespace();
+            let frame = words
+                .next()
+                .and_then(|word| word.parse().ok())
+                .ok_or_else(|| error("expected a frame number".into()))?;
+            let controller = match words.next() {
+                Some("1") => 0,
+                Some("2") => 1,
+                _ => return Err(error("expected controller 1 or 2".into())),
+            };
+
+            let mut buttons = Controller::empty();
+            for word in words {
+                buttons |= button(word).ok_or_else(|| error(format!("unknown button {word}")))?;
+            }
+
+            changes.push(Change {
+                frame,
+                controller,
+                buttons,
+            });
+        }
+
+        // stable, so later lines for the same frame win
+        changes.sort_by_key(|change| change.frame);
+        Ok(InputScript { changes })
+    }
+
+    /// Applies the changes made on `frame` to the controllers.
+    pub fn apply(&self, frame: u32, controllers: &mut [Controller; 2]) {
+        for change in self.changes.iter().filter(|change| change.frame == frame) {
+            controllers[change.controller] = change.buttons;
+        }
+    }
+}
+
+fn button(name: &str) -> Option<Controller> {
+    let button = match name.to_ascii_uppercase().as_str() {
+        "A" => Controller::BUTTON_A,
+        "B" => Controller::BUTTON_B,
+        "SELECT" => Controller::SELECT,
+        "START" => Controller::START,
+        "UP" => Controller::UP,
+        "DOWN" => Controller::DOWN,
+        "LEFT" => Controller::LEFT,
+        "RIGHT" => Controller::RIGHT,
+        _ => return None,
+    };
+    Some(button)
+}
+
+#[test]
+fn test_input_script() {
+    let script = InputScript::parse("# comment\n30 1 START\n31 1\n30 2 a right\n").unwrap();
+    let mut controllers = [Controller::empty(); 2];
+
+    script.apply(30, &mut controllers);
+    assert_eq!(controllers[0], Controller::START);
+    assert_eq!(controllers[1], Controller::BUTTON_A | Controller::RIGHT);
+
+    script.apply(31, &mut controllers);
+    assert_eq!(controllers[0], Controller::empty());
+    assert_eq!(controllers[1], Controller::BUTTON_A | Controller::RIGHT);
+
+    assert!(InputScript::parse("10 3 A").is_err());
+    assert!(InputScript::parse("10 1 TURBO").is_err());
+}